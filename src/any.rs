@@ -0,0 +1,302 @@
+//! Defines the [AnyPool] which keeps values of many types.
+
+use crate::{TypePool, PoolKey, PoolError, RawPoolKey, iter, storage::DefaultStorage,};
+use std::{
+  ops, option,
+  any::{Any, TypeId,},
+  iter::Flatten,
+  collections::HashMap,
+};
+
+/// An iterator over the [PoolKey]s and values of one type in an [AnyPool].
+pub type TypeIter<'a, T,> = Flatten<option::IntoIter<iter::Iter<'a, T, DefaultStorage<T,>,>>>;
+/// An iterator over the [PoolKey]s and mutable values of one type in an [AnyPool].
+pub type TypeIterMut<'a, T,> = Flatten<option::IntoIter<iter::IterMut<'a, T, DefaultStorage<T,>,>>>;
+
+/// A type erased [TypePool] of an [AnyPool].
+trait AnyTypePool: Any {
+  /// Returns the number of values in the pool.
+  fn len(&self,) -> usize;
+  /// Returns `true` if `key` was issued by the pool.
+  fn owns_raw(&self, key: &RawPoolKey,) -> bool;
+  /// Returns `true` if the pool contains `key`.
+  fn contains_raw(&self, key: &RawPoolKey,) -> bool;
+  /// Returns the value mapped too `key`.
+  fn get_raw(&self, key: RawPoolKey,) -> Option<&dyn Any>;
+  /// Removes the value mapped too `key` and returns `true` if it existed.
+  fn remove_raw(&mut self, key: RawPoolKey,) -> bool;
+  /// Returns the pool as [Any] so it can be downcast.
+  fn as_any(&self,) -> &dyn Any;
+  /// Returns the pool as mutable [Any] so it can be downcast.
+  fn as_any_mut(&mut self,) -> &mut dyn Any;
+}
+
+impl<T,> AnyTypePool for TypePool<T,>
+  where T: 'static, {
+  #[inline]
+  fn len(&self,) -> usize { TypePool::len(self,) }
+  #[inline]
+  fn owns_raw(&self, key: &RawPoolKey,) -> bool { key.pool_id() == self.pool_id }
+  fn contains_raw(&self, key: &RawPoolKey,) -> bool {
+    key.downcast::<T,>().is_some_and(|key,| self.contains_key(&key,),)
+  }
+  fn get_raw(&self, key: RawPoolKey,) -> Option<&dyn Any> {
+    self.get(key.downcast::<T,>()?,).ok().map(|value,| value as &dyn Any,)
+  }
+  fn remove_raw(&mut self, key: RawPoolKey,) -> bool {
+    match key.downcast::<T,>() {
+      Some(key,) => self.try_remove(key,).is_ok(),
+      None => false,
+    }
+  }
+  #[inline]
+  fn as_any(&self,) -> &dyn Any { self }
+  #[inline]
+  fn as_any_mut(&mut self,) -> &mut dyn Any { self }
+}
+
+/// A pool of values of any `'static` type.
+/// 
+/// Each type of value is kept in its own [TypePool], so every [PoolKey] issued by an
+/// AnyPool is typed and indexing with it is as fast as indexing a TypePool.
+/// 
+/// Keys can be erased to [RawPoolKey]s to keep keys of different types together.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::{AnyPool, RawPoolKey,};
+/// 
+/// let mut pool = AnyPool::new();
+/// let name = pool.insert("player",);
+/// let health = pool.insert(100u32,);
+/// 
+/// pool[health] -= 10;
+/// assert_eq!(pool[name], "player");
+/// assert_eq!(pool.get(health,), Ok(&90));
+/// 
+/// let keys = [RawPoolKey::from(name,), RawPoolKey::from(health,),];
+/// assert!(keys.iter().all(|key,| pool.contains_raw(key,),));
+/// ```
+#[derive(Default,)]
+pub struct AnyPool {
+  pools: HashMap<TypeId, Box<dyn AnyTypePool>>,
+}
+
+impl AnyPool {
+  /// Returns a new empty AnyPool.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+  /// Returns the [TypePool] of the `T` values of this AnyPool.
+  /// 
+  /// Returns `None` if no `T` value has been inserted.
+  pub fn pool<T,>(&self,) -> Option<&TypePool<T,>>
+    where T: 'static, {
+    self.pools.get(&TypeId::of::<T,>(),).map(|pool,| pool.as_any().downcast_ref().unwrap(),)
+  }
+  /// Returns the mutable [TypePool] of the `T` values of this AnyPool.
+  /// 
+  /// Returns `None` if no `T` value has been inserted.
+  pub fn pool_mut<T,>(&mut self,) -> Option<&mut TypePool<T,>>
+    where T: 'static, {
+    self.pools.get_mut(&TypeId::of::<T,>(),).map(|pool,| pool.as_any_mut().downcast_mut().unwrap(),)
+  }
+  /// Returns the [TypePool] of the `T` values of this AnyPool, creating it if needed.
+  fn pool_or_default<T,>(&mut self,) -> &mut TypePool<T,>
+    where T: 'static, {
+    self.pools.entry(TypeId::of::<T,>(),)
+    .or_insert_with(|| Box::new(TypePool::<T,>::new(),),)
+    .as_any_mut().downcast_mut().unwrap()
+  }
+  /// Returns the number of values of every type in this AnyPool.
+  pub fn len(&self,) -> usize { self.pools.values().map(|pool,| pool.len(),).sum() }
+  /// Returns `true` if this AnyPool is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Returns the number of `T` values in this AnyPool.
+  #[inline]
+  pub fn len_of<T,>(&self,) -> usize
+    where T: 'static, {
+    self.pool::<T,>().map_or(0, TypePool::len,)
+  }
+  /// Returns `true` if `key` was issued by this AnyPool.
+  #[inline]
+  pub fn owns_key<T,>(&self, key: &PoolKey<T,>,) -> bool
+    where T: 'static, {
+    self.pool::<T,>().is_some_and(|pool,| pool.owns_key(key,),)
+  }
+  /// Returns `true` if this AnyPool contains `key`.
+  #[inline]
+  pub fn contains_key<T,>(&self, key: &PoolKey<T,>,) -> bool
+    where T: 'static, {
+    self.pool::<T,>().is_some_and(|pool,| pool.contains_key(key,),)
+  }
+  /// Inserts `value` into the AnyPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the AnyPool is full of `T` values.
+  pub fn insert<T,>(&mut self, value: T,) -> PoolKey<T,>
+    where T: 'static, {
+    self.pool_or_default().insert(value,)
+  }
+  /// Attempts to insert `value` into the AnyPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the AnyPool is full of `T`
+  /// values.
+  pub fn try_insert<T,>(&mut self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)>
+    where T: 'static, {
+    self.pool_or_default().try_insert(value,)
+  }
+  /// Returns a reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get<T,>(&self, key: PoolKey<T,>,) -> Result<&T, PoolError>
+    where T: 'static, {
+    self.pool().ok_or(PoolError::ForeignKey,)?.get(key,)
+  }
+  /// Returns a mutable reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get_mut<T,>(&mut self, key: PoolKey<T,>,) -> Result<&mut T, PoolError>
+    where T: 'static, {
+    self.pool_mut().ok_or(PoolError::ForeignKey,)?.get_mut(key,)
+  }
+  /// Removes the value mapped too `key`.
+  /// 
+  /// Returns `None` if the value has already been removed.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is not owned by this AnyPool.
+  pub fn remove<T,>(&mut self, key: PoolKey<T,>,) -> Option<T>
+    where T: 'static, {
+    assert!(self.owns_key(&key,), "`AnyPool::remove` `key` must be owned by this pool",);
+
+    self.try_remove(key,).ok()
+  }
+  /// Attempts to remove the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  pub fn try_remove<T,>(&mut self, key: PoolKey<T,>,) -> Result<T, PoolError>
+    where T: 'static, {
+    self.pool_mut().ok_or(PoolError::ForeignKey,)?.try_remove(key,)
+  }
+  /// Returns the [TypePool] which issued `key`.
+  fn raw_pool(&self, key: &RawPoolKey,) -> Option<&dyn AnyTypePool> {
+    match key.type_id() {
+      Some(type_id,) => self.pools.get(&type_id,).map(Box::as_ref,),
+      //Without a type the pool is found by its identity.
+      None => self.pools.values().map(Box::as_ref,).find(|pool,| pool.owns_raw(key,),),
+    }
+  }
+  /// Returns `true` if this AnyPool contains the value of `key`.
+  #[inline]
+  pub fn contains_raw(&self, key: &RawPoolKey,) -> bool {
+    self.raw_pool(key,).is_some_and(|pool,| pool.contains_raw(key,),)
+  }
+  /// Returns a type erased reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get_raw(&self, key: RawPoolKey,) -> Option<&dyn Any> {
+    self.raw_pool(&key,)?.get_raw(key,)
+  }
+  /// Removes the value mapped too `key` without knowing its type.
+  /// 
+  /// Returns `false` if this AnyPool does not contain the value.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  pub fn remove_raw(&mut self, key: RawPoolKey,) -> bool {
+    let pool = match key.type_id() {
+      Some(type_id,) => self.pools.get_mut(&type_id,),
+      None => self.pools.values_mut().find(|pool,| pool.owns_raw(&key,),),
+    };
+
+    pool.is_some_and(|pool,| pool.remove_raw(key,),)
+  }
+  /// Returns an iterator over the [PoolKey]s and values of the `T` values of this AnyPool.
+  #[inline]
+  pub fn iter<T,>(&self,) -> TypeIter<'_, T,>
+    where T: 'static, {
+    self.pool::<T,>().map(TypePool::iter,).into_iter().flatten()
+  }
+  /// Returns an iterator over the [PoolKey]s and mutable values of the `T` values of this
+  /// AnyPool.
+  #[inline]
+  pub fn iter_mut<T,>(&mut self,) -> TypeIterMut<'_, T,>
+    where T: 'static, {
+    self.pool_mut::<T,>().map(TypePool::iter_mut,).into_iter().flatten()
+  }
+}
+
+impl<T,> ops::Index<PoolKey<T,>> for AnyPool
+  where T: 'static, {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`AnyPool::index` {}", e),)
+  }
+}
+
+impl<T,> ops::IndexMut<PoolKey<T,>> for AnyPool
+  where T: 'static, {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`AnyPool::index_mut` {}", e),)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_any_pool() {
+    let mut pool = AnyPool::new();
+    let ints = (0..5).map(|value,| pool.insert(value,),).collect::<Vec<_>>();
+    let strings = ["a", "b",].iter().map(|&value,| pool.insert(String::from(value,),),).collect::<Vec<_>>();
+
+    assert_eq!(pool.len(), 7, "`AnyPool::len` is wrong",);
+    assert_eq!(pool.len_of::<String,>(), 2, "`AnyPool::len_of` is wrong",);
+    assert_eq!(pool.len_of::<u8,>(), 0, "`AnyPool::len_of` is wrong",);
+    assert_eq!(pool[ints[3]], 3, "`AnyPool::get` returned wrong value",);
+    assert_eq!(pool[strings[1]], "b", "`AnyPool::get` returned wrong value",);
+
+    for (_, value,) in pool.iter_mut::<i32,>() { *value *= 2; }
+    assert_eq!(pool.iter::<i32,>().map(|(_, value,),| *value,).sum::<i32>(), 20, "`AnyPool::iter_mut` missed values",);
+    assert_eq!(pool.iter::<u8,>().count(), 0, "`AnyPool::iter` returned values of a missing type",);
+
+    let raw = RawPoolKey::from(strings[0],);
+    assert_eq!(pool.get_raw(raw,).and_then(<dyn Any>::downcast_ref::<String>,).map(String::as_str,), Some("a"), "`AnyPool::get_raw` returned wrong value",);
+    assert!(pool.remove_raw(raw,), "`AnyPool::remove_raw` did not remove the value",);
+    assert!(!pool.contains_raw(&raw,), "`AnyPool::remove_raw` kept the value",);
+    assert!(!pool.remove_raw(raw,), "`AnyPool::remove_raw` removed a missing value",);
+    assert_eq!(pool.get(strings[0],), Err(PoolError::StaleKey), "`AnyPool::get` accepted a stale key",);
+
+    let untyped = strings[1].erase_untyped();
+    assert!(pool.contains_raw(&untyped,), "`AnyPool::contains_raw` missed an untyped key",);
+    assert!(pool.remove_raw(untyped,), "`AnyPool::remove_raw` did not remove an untyped key",);
+
+    let foreign = TypePool::new().insert(0u8,);
+    assert_eq!(pool.get(foreign,), Err(PoolError::ForeignKey), "`AnyPool::get` accepted a foreign key",);
+    assert_eq!(pool.remove(ints[0],), Some(0), "`AnyPool::remove` returned wrong value",);
+    assert!(!pool.contains_key(&ints[0],), "`AnyPool::remove` kept the value",);
+  }
+}
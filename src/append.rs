@@ -0,0 +1,262 @@
+//! Defines the [AppendPool] which can be inserted into concurrently without locking.
+
+use crate::{PoolKey, PoolError, KeyState, next_pool_id,};
+use std::{
+  ops, ptr,
+  cell::UnsafeCell,
+  mem::MaybeUninit,
+  num::NonZeroUsize,
+  marker::PhantomData,
+  sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering,},
+};
+
+/// The base two log of the number of slots in the first segment.
+const FIRST_SEGMENT_BITS: u32 = 5;
+/// The number of segments in an [AppendPool].
+const SEGMENTS: usize = (usize::BITS - FIRST_SEGMENT_BITS) as usize;
+/// The number of slots in all of the segments of an [AppendPool].
+const CAPACITY: usize = usize::MAX - (1 << FIRST_SEGMENT_BITS) + 1;
+
+/// A slot in an [AppendPool] segment.
+struct Slot<T,> {
+  /// `true` once `value` has been written.
+  ready: AtomicBool,
+  value: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T,> Slot<T,> {
+  /// Returns a new empty Slot.
+  #[inline]
+  fn new() -> Self {
+    Self { ready: AtomicBool::new(false,), value: UnsafeCell::new(MaybeUninit::uninit(),), }
+  }
+}
+
+impl<T,> Drop for Slot<T,> {
+  fn drop(&mut self,) {
+    if *self.ready.get_mut() { unsafe { (*self.value.get()).assume_init_drop() } }
+  }
+}
+
+/// Returns the segment holding `index` and the offset of `index` in that segment.
+#[inline]
+fn locate(index: usize,) -> (usize, usize,) {
+  //Shifting by the size of the first segment makes every segment start at a power of two.
+  let shifted = index as u128 + (1 << FIRST_SEGMENT_BITS);
+  let segment = (127 - shifted.leading_zeros() - FIRST_SEGMENT_BITS) as usize;
+
+  (segment, (shifted - (1 << (segment as u32 + FIRST_SEGMENT_BITS))) as usize,)
+}
+
+/// Returns the number of slots in `segment`.
+#[inline]
+fn segment_len(segment: usize,) -> usize { 1 << (segment as u32 + FIRST_SEGMENT_BITS) }
+
+/// A pool of `T` values which can only grow.
+/// 
+/// Values are never removed or moved, so inserting and reading values only needs a shared
+/// reference and never blocks. References to values remain valid while other threads
+/// insert more values.
+/// 
+/// Values are stored in segments which double in size as the pool grows.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::AppendPool;
+/// use std::thread;
+/// 
+/// let pool = AppendPool::new();
+/// let first = pool.insert("first",);
+/// let value = &pool[first];
+/// 
+/// thread::scope(|scope,| for _ in 0..4 {
+///   scope.spawn(|| for _ in 0..100 { pool.insert("other",); },);
+/// },);
+/// assert_eq!(*value, "first");
+/// assert_eq!(pool.len(), 401);
+/// ```
+pub struct AppendPool<T,> {
+  segments: [AtomicPtr<Slot<T,>>; SEGMENTS],
+  next_id: AtomicUsize,
+  pool_id: NonZeroUsize,
+  _values: PhantomData<T,>,
+}
+
+unsafe impl<T: Send,> Send for AppendPool<T,> {}
+
+//Values can be inserted from and read by any thread.
+unsafe impl<T: Send + Sync,> Sync for AppendPool<T,> {}
+
+impl<T,> AppendPool<T,> {
+  /// Returns a new empty AppendPool.
+  pub fn new() -> Self {
+    Self {
+      segments: std::array::from_fn(|_,| AtomicPtr::new(ptr::null_mut(),),),
+      next_id: AtomicUsize::new(0,),
+      pool_id: next_pool_id(),
+      _values: PhantomData,
+    }
+  }
+  /// Returns the first slot of `segment`, allocating the segment if it does not exist.
+  fn segment(&self, segment: usize,) -> *mut Slot<T,> {
+    let slots = self.segments[segment].load(Ordering::Acquire,);
+
+    if !slots.is_null() { return slots }
+
+    let new = Box::into_raw((0..segment_len(segment,)).map(|_,| Slot::<T,>::new(),).collect::<Box<[_]>>(),) as *mut Slot<T,>;
+
+    match self.segments[segment].compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire,) {
+      Ok(_,) => new,
+      //Another thread allocated the segment first.
+      Err(slots,) => {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(new, segment_len(segment,),),) },);
+        slots
+      },
+    }
+  }
+  /// Returns the slot of `key` if it exists.
+  fn slot(&self, key: &PoolKey<T,>,) -> Option<&Slot<T,>> {
+    let (segment, offset,) = locate(key.0,);
+    let slots = self.segments[segment].load(Ordering::Acquire,);
+
+    if slots.is_null() { None } else { Some(unsafe { &*slots.add(offset,) }) }
+  }
+  /// Returns `true` if `key` was issued by this AppendPool.
+  #[inline]
+  pub fn owns_key(&self, key: &PoolKey<T,>,) -> bool { key.1 == self.pool_id }
+  /// Returns the [KeyState] of `key` in this AppendPool.
+  /// 
+  /// Values are never removed so a key is never [KeyState::Removed].
+  pub fn key_state(&self, key: &PoolKey<T,>,) -> KeyState {
+    //Every value is given its id as its generation.
+    if !self.owns_key(key,) || key.0 != key.2 || key.0 >= CAPACITY { return KeyState::Unknown }
+
+    match self.slot(key,) {
+      Some(slot,) if slot.ready.load(Ordering::Acquire,) => KeyState::Live,
+      _ => KeyState::Unknown,
+    }
+  }
+  /// Returns `true` if this AppendPool contains `key`.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool { self.key_state(key,) == KeyState::Live }
+  /// Returns the number of values in this AppendPool.
+  /// 
+  /// This includes values which other threads are still inserting.
+  #[inline]
+  pub fn len(&self,) -> usize { self.next_id.load(Ordering::Relaxed,) }
+  /// Returns `true` if this AppendPool is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Inserts `value` into the AppendPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the AppendPool is full.
+  pub fn insert(&self, value: T,) -> PoolKey<T,> {
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("{}", e),)
+  }
+  /// Attempts to insert `value` into the AppendPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the AppendPool is full.
+  pub fn try_insert(&self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)> {
+    let id = match self.next_id.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id,| Some(id + 1,).filter(|_,| id < CAPACITY,),) {
+      Ok(id,) => id,
+      Err(_,) => return Err((PoolError::CapacityExhausted, value,)),
+    };
+    let (segment, offset,) = locate(id,);
+    let slot = unsafe { &*self.segment(segment,).add(offset,) };
+
+    //No other thread accesses the slot until it is marked as ready.
+    unsafe { (*slot.value.get()).write(value,); }
+    slot.ready.store(true, Ordering::Release,);
+
+    Ok(PoolKey(id, self.pool_id, id, PhantomData,))
+  }
+  /// Returns a reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&T, PoolError> {
+    match self.key_state(&key,) {
+      //The value is never moved or modified once it is ready.
+      KeyState::Live => Ok(unsafe { (*self.slot(&key,).unwrap().value.get()).assume_init_ref() }),
+      _ if self.owns_key(&key,) => Err(PoolError::MissingKey),
+      _ => Err(PoolError::ForeignKey),
+    }
+  }
+}
+
+impl<T,> Default for AppendPool<T,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T,> ops::Index<PoolKey<T,>> for AppendPool<T,> {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`AppendPool::index` {}", e),)
+  }
+}
+
+impl<T,> Drop for AppendPool<T,> {
+  fn drop(&mut self,) {
+    for (segment, slots,) in self.segments.iter_mut().enumerate() {
+      let slots = *slots.get_mut();
+
+      if !slots.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(slots, segment_len(segment,),),) },);
+      }
+    }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use std::{thread, rc::Rc,};
+
+  #[test]
+  fn test_locate() {
+    assert_eq!(locate(0,), (0, 0,), "`locate` returned the wrong slot",);
+    assert_eq!(locate(31,), (0, 31,), "`locate` returned the wrong slot",);
+    assert_eq!(locate(32,), (1, 0,), "`locate` returned the wrong slot",);
+    assert_eq!(locate(95,), (1, 63,), "`locate` returned the wrong slot",);
+    assert_eq!(locate(96,), (2, 0,), "`locate` returned the wrong slot",);
+    assert_eq!(locate(CAPACITY - 1,), (SEGMENTS - 1, segment_len(SEGMENTS - 1,) - 1,), "`locate` returned the wrong slot",);
+  }
+  #[test]
+  fn test_append_pool() {
+    let pool = AppendPool::new();
+    let keys = thread::scope(|scope,| {
+      let handles = (0..4).map(|t,| {
+        let pool = &pool;
+
+        scope.spawn(move || (0..500).map(|i,| (pool.insert(t * 1000 + i,), t * 1000 + i,),).collect::<Vec<_>>(),)
+      },).collect::<Vec<_>>();
+
+      handles.into_iter().flat_map(|handle,| handle.join().unwrap(),).collect::<Vec<_>>()
+    },);
+
+    assert_eq!(pool.len(), 2000, "`AppendPool::insert` lost values",);
+    assert!(keys.iter().all(|&(key, value,),| pool[key] == value,), "`AppendPool::get` returned the wrong value",);
+
+    let forged = PoolKey(2000, pool.pool_id, 2000, PhantomData,);
+    assert_eq!(pool.get(forged,).err(), Some(PoolError::MissingKey), "`AppendPool::get` accepted a missing key",);
+    assert_eq!(AppendPool::new().get(keys[0].0,).err(), Some(PoolError::ForeignKey), "`AppendPool::get` accepted a foreign key",);
+  }
+  #[test]
+  fn test_append_pool_drop() {
+    let value = Rc::new(0,);
+    let pool = AppendPool::new();
+
+    for _ in 0..100 { pool.insert(value.clone(),); }
+    drop(pool,);
+    assert_eq!(Rc::strong_count(&value,), 1, "`AppendPool::drop` leaked values",);
+  }
+}
@@ -0,0 +1,104 @@
+//! Defines the [VacantEntry] API for inserting values which know their own [PoolKey].
+
+use crate::{TypePool, PoolKey, PoolError, storage::PoolStorage,};
+use std::marker::PhantomData;
+
+/// A reserved but unfilled [PoolKey] in a [TypePool].
+/// 
+/// The key is only issued to a value if [VacantEntry::insert] is called, dropping the
+/// VacantEntry abandons the reservation and leaves the TypePool unchanged.
+pub struct VacantEntry<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  pool: &'a mut TypePool<T, S,>,
+  key: PoolKey<T,>,
+}
+
+impl<'a, T, S,> VacantEntry<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  /// Returns the [PoolKey] the inserted value will have.
+  #[inline]
+  pub fn key(&self,) -> PoolKey<T,> { self.key }
+  /// Inserts `value` under the reserved [PoolKey] and returns a reference to it.
+  pub fn insert(self, value: T,) -> &'a mut T {
+    let key = self.pool.insert(value,);
+
+    debug_assert!(key == self.key, "`VacantEntry::insert` reserved key was not issued",);
+    &mut self.pool[key]
+  }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Reserves the next [PoolKey] of this TypePool.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, PoolKey,};
+  /// 
+  /// struct Node { this: PoolKey<Node>, }
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let entry = pool.vacant_entry().unwrap();
+  /// let key = entry.key();
+  /// 
+  /// entry.insert(Node { this: key, },);
+  /// assert!(pool[key].this == key);
+  /// ```
+  pub fn vacant_entry(&mut self,) -> Result<VacantEntry<'_, T, S,>, PoolError> {
+    let generation = self.next_generation;
+    let index = self.pool.next_index()
+      .filter(|_,| generation != usize::MAX,)
+      .ok_or(PoolError::CapacityExhausted,)?;
+    let key = PoolKey(index, self.pool_id, generation, PhantomData,);
+
+    Ok(VacantEntry { pool: self, key, },)
+  }
+  /// Inserts the value returned by `value` into the TypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Params
+  /// 
+  /// value --- Called with the [PoolKey] the value will have.  
+  /// 
+  /// # Panics
+  /// 
+  /// If the TypePool is full.
+  pub fn insert_with<F,>(&mut self, value: F,) -> PoolKey<T,>
+    where F: FnOnce(PoolKey<T,>,) -> T, {
+    let entry = self.vacant_entry().unwrap_or_else(|e,| panic!("{}", e),);
+    let key = entry.key();
+
+    entry.insert(value(key,),);
+    key
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::storage::HashStorage;
+
+  /// A value which stores its own key.
+  #[derive(PartialEq, Debug,)]
+  struct Node(PoolKey<Node,>, i32,);
+
+  #[test]
+  fn test_vacant_entry() {
+    let mut pool = TypePool::<_, HashStorage<_,>,>::default();
+    let key1 = pool.insert_with(|key,| Node(key, 1,),);
+
+    assert_eq!(pool[key1].0, key1, "`TypePool::insert_with` passed the wrong key",);
+
+    let abandoned = pool.vacant_entry().expect("`TypePool::vacant_entry` failed",).key();
+    assert_eq!(pool.len(), 1, "`VacantEntry` inserted a value when dropped",);
+    assert!(!pool.contains_key(&abandoned,), "`VacantEntry` issued an abandoned key",);
+
+    let entry = pool.vacant_entry().expect("`TypePool::vacant_entry` failed",);
+    let key2 = entry.key();
+    assert_eq!(key2.0, abandoned.0, "`VacantEntry` did not free the abandoned slot",);
+    entry.insert(Node(key2, 2,),).1 += 1;
+    assert_eq!(pool[key2], Node(key2, 3,), "`VacantEntry::insert` returned the wrong value",);
+  }
+}
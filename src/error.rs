@@ -0,0 +1,34 @@
+//! Defines the errors returned by the fallible [TypePool](crate::TypePool) operations.
+
+use std::{fmt, error,};
+
+/// An error from a fallible [TypePool](crate::TypePool) operation.
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum PoolError {
+  /// The key was issued by a different pool.
+  ForeignKey,
+  /// The key was never issued by the pool.
+  MissingKey,
+  /// The value the key referred to has been removed.
+  StaleKey,
+  /// The same key was passed more than once to a multi key access.
+  DuplicateKey,
+  /// The pool cannot hold any more values.
+  CapacityExhausted,
+}
+
+impl fmt::Display for PoolError {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    let msg = match self {
+      PoolError::ForeignKey => "`key` must be issued from the pool",
+      PoolError::MissingKey => "`key` does not exist",
+      PoolError::StaleKey => "`key` has been removed",
+      PoolError::DuplicateKey => "`key` was passed more than once",
+      PoolError::CapacityExhausted => "`TypePool` is full",
+    };
+
+    fmt.write_str(msg,)
+  }
+}
+
+impl error::Error for PoolError {}
@@ -0,0 +1,405 @@
+//! Defines the [HistoryPool] which can undo and redo changes to its values.
+
+use crate::{TypePool, PoolKey, PoolError, storage::{PoolStorage, DefaultStorage,},};
+use std::{ops, mem, collections::{HashSet, VecDeque,},};
+
+/// A change to a [TypePool] which can be applied to get the change which reverts it.
+enum Edit<T,> {
+  /// Removes the value of the key.
+  Remove(PoolKey<T,>,),
+  /// Inserts the value back under the removed key.
+  Restore(PoolKey<T,>, T,),
+  /// Replaces the value of the key.
+  Replace(PoolKey<T,>, T,),
+}
+
+impl<T,> Edit<T,> {
+  /// Applies this Edit to `pool` and returns the Edit which reverts it.
+  fn apply<S,>(self, pool: &mut TypePool<T, S,>,) -> Self
+    where S: PoolStorage<T,>, {
+    match self {
+      Edit::Remove(key,) => {
+        let value = pool.try_remove(key,).unwrap_or_else(|e,| panic!("`HistoryPool` history is corrupted {}", e),);
+
+        Edit::Restore(key, value,)
+      },
+      Edit::Restore(key, value,) => {
+        pool.restore(key, value,);
+        Edit::Remove(key,)
+      },
+      Edit::Replace(key, value,) => Edit::Replace(key, mem::replace(&mut pool[key], value,),),
+    }
+  }
+}
+
+/// A named group of [Edit]s which are undone or redone together.
+struct Checkpoint<T,> {
+  name: Option<&'static str>,
+  /// The edits of the checkpoint, applied last to first.
+  edits: Vec<Edit<T,>>,
+}
+
+impl<T,> Checkpoint<T,> {
+  /// Applies this Checkpoint to `pool` and returns the Checkpoint which reverts it.
+  fn apply<S,>(self, pool: &mut TypePool<T, S,>,) -> Self
+    where S: PoolStorage<T,>, {
+    //The reverting edits are collected in reverse so they are also applied last to first.
+    let edits = self.edits.into_iter().rev().map(|edit,| edit.apply(pool,),).collect();
+
+    Checkpoint { name: self.name, edits, }
+  }
+}
+
+/// A [TypePool] which records its changes so they can be undone and redone.
+/// 
+/// Changes are grouped into checkpoints by [HistoryPool::checkpoint] and undone one
+/// checkpoint at a time. Undoing restores removed values under their original
+/// [PoolKey]s, so keys held elsewhere stay valid.
+/// 
+/// Undoing needs whatever a change overwrote, so [HistoryPool::get_mut] clones a value
+/// before the first change to it in a checkpoint and [HistoryPool::remove] keeps the
+/// removed value. Values are read through the TypePool the HistoryPool dereferences to.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::HistoryPool;
+/// 
+/// let mut pool = HistoryPool::new();
+/// let key = pool.insert(1,);
+/// pool.checkpoint("insert",);
+/// 
+/// pool[key] = 2;
+/// pool.remove(key,);
+/// pool.checkpoint("edit",);
+/// 
+/// assert!(!pool.contains_key(&key,));
+/// assert_eq!(pool.undo(), Some(Some("edit")));
+/// assert_eq!(pool[key], 1);
+/// assert_eq!(pool.redo(), Some(Some("edit")));
+/// assert!(!pool.contains_key(&key,));
+/// ```
+pub struct HistoryPool<T, S = DefaultStorage<T,>,>
+  where S: PoolStorage<T,>, {
+  pool: TypePool<T, S,>,
+  /// The edits made since the last checkpoint.
+  pending: Vec<Edit<T,>>,
+  /// The keys whose current values are already restored by `pending`.
+  saved: HashSet<PoolKey<T,>>,
+  undo: VecDeque<Checkpoint<T,>>,
+  redo: Vec<Checkpoint<T,>>,
+  limit: usize,
+}
+
+impl<T,> HistoryPool<T,> {
+  /// Returns a new empty HistoryPool.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+  /// Returns a new empty HistoryPool which keeps at most `limit` checkpoints.
+  #[inline]
+  pub fn with_history_limit(limit: usize,) -> Self { Self { limit, ..Self::default() } }
+}
+
+impl<T, S,> HistoryPool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Returns the TypePool of this HistoryPool, discarding its history.
+  #[inline]
+  pub fn into_inner(self,) -> TypePool<T, S,> { self.pool }
+  /// Returns the number of checkpoints this HistoryPool keeps.
+  #[inline]
+  pub fn history_limit(&self,) -> usize { self.limit }
+  /// Sets the number of checkpoints this HistoryPool keeps, discarding the oldest
+  /// checkpoints beyond `limit`.
+  pub fn set_history_limit(&mut self, limit: usize,) {
+    self.limit = limit;
+    self.trim();
+  }
+  /// Discards the oldest checkpoints beyond the history limit.
+  fn trim(&mut self,) {
+    while self.undo.len() > self.limit { self.undo.pop_front(); }
+  }
+  /// Records `edit` as the next edit since the last checkpoint.
+  fn record(&mut self, edit: Edit<T,>,) {
+    self.redo.clear();
+    self.pending.push(edit,);
+  }
+  /// Groups the edits made since the last checkpoint into a checkpoint called `name`.
+  fn seal(&mut self, name: Option<&'static str>,) {
+    if self.pending.is_empty() { return }
+
+    self.saved.clear();
+    self.undo.push_back(Checkpoint { name, edits: mem::take(&mut self.pending,), },);
+    self.trim();
+  }
+  /// Groups the changes made since the last checkpoint into a checkpoint called `name`.
+  /// 
+  /// Does nothing if nothing has changed since the last checkpoint.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the checkpoint returned when it is undone or redone.
+  #[inline]
+  pub fn checkpoint(&mut self, name: &'static str,) { self.seal(Some(name,),) }
+  /// Returns `true` if there are changes which can be undone.
+  #[inline]
+  pub fn can_undo(&self,) -> bool { !self.pending.is_empty() || !self.undo.is_empty() }
+  /// Returns `true` if there are changes which can be redone.
+  #[inline]
+  pub fn can_redo(&self,) -> bool { !self.redo.is_empty() }
+  /// Undoes the changes of the last checkpoint.
+  /// 
+  /// Changes made since the last checkpoint are undone as an unnamed checkpoint.
+  /// 
+  /// Returns the name of the undone checkpoint or `None` if there is nothing to undo.
+  pub fn undo(&mut self,) -> Option<Option<&'static str>> {
+    self.seal(None,);
+
+    let checkpoint = self.undo.pop_back()?.apply(&mut self.pool,);
+    let name = checkpoint.name;
+
+    self.redo.push(checkpoint,);
+    Some(name,)
+  }
+  /// Redoes the changes of the last undone checkpoint.
+  /// 
+  /// Returns the name of the redone checkpoint or `None` if there is nothing to redo.
+  pub fn redo(&mut self,) -> Option<Option<&'static str>> {
+    let checkpoint = self.redo.pop()?.apply(&mut self.pool,);
+    let name = checkpoint.name;
+
+    self.undo.push_back(checkpoint,);
+    self.trim();
+    Some(name,)
+  }
+  /// Discards every checkpoint, keeping the current values.
+  pub fn clear_history(&mut self,) {
+    self.pending.clear();
+    self.saved.clear();
+    self.undo.clear();
+    self.redo.clear();
+  }
+  /// Inserts `value` into the HistoryPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the HistoryPool is full.
+  pub fn insert(&mut self, value: T,) -> PoolKey<T,> {
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("{}", e),)
+  }
+  /// Attempts to insert `value` into the HistoryPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the HistoryPool is full.
+  pub fn try_insert(&mut self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)> {
+    let key = self.pool.try_insert(value,)?;
+
+    self.record(Edit::Remove(key,),);
+    self.saved.insert(key,);
+    Ok(key)
+  }
+  /// Removes the value mapped too `key`.
+  /// 
+  /// The value is kept in the history so the removal can be undone.
+  /// 
+  /// Returns `false` if the value has already been removed.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is not owned by this HistoryPool.
+  pub fn remove(&mut self, key: PoolKey<T,>,) -> bool {
+    assert!(self.pool.owns_key(&key,), "`HistoryPool::remove` `key` must be owned by this pool",);
+
+    self.try_remove(key,).is_ok()
+  }
+  /// Attempts to remove the value mapped too `key`.
+  /// 
+  /// The value is kept in the history so the removal can be undone.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<(), PoolError> {
+    let value = self.pool.try_remove(key,)?;
+
+    self.record(Edit::Restore(key, value,),);
+    Ok(())
+  }
+}
+
+impl<T, S,> HistoryPool<T, S,>
+  where T: Clone, S: PoolStorage<T,>, {
+  /// Returns a mutable reference to the value mapped too `key`.
+  /// 
+  /// The first time a value is accessed mutably after a checkpoint it is cloned so the
+  /// change can be undone.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut T, PoolError> {
+    if !self.saved.contains(&key,) {
+      let value = self.pool.get(key,)?.clone();
+
+      self.saved.insert(key,);
+      self.record(Edit::Replace(key, value,),);
+    }
+
+    self.pool.get_mut(key,)
+  }
+}
+
+impl<T, S,> Default for HistoryPool<T, S,>
+  where S: PoolStorage<T,>, {
+  #[inline]
+  fn default() -> Self {
+    Self {
+      pool: TypePool::default(),
+      pending: Vec::new(),
+      saved: HashSet::new(),
+      undo: VecDeque::new(),
+      redo: Vec::new(),
+      limit: usize::MAX,
+    }
+  }
+}
+
+impl<T, S,> From<TypePool<T, S,>> for HistoryPool<T, S,>
+  where S: PoolStorage<T,>, {
+  #[inline]
+  fn from(pool: TypePool<T, S,>,) -> Self { Self { pool, ..Self::default() } }
+}
+
+impl<T, S,> ops::Deref for HistoryPool<T, S,>
+  where S: PoolStorage<T,>, {
+  type Target = TypePool<T, S,>;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { &self.pool }
+}
+
+impl<T, S,> ops::Index<PoolKey<T,>> for HistoryPool<T, S,>
+  where S: PoolStorage<T,>, {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output { &self.pool[key] }
+}
+
+impl<T, S,> ops::IndexMut<PoolKey<T,>> for HistoryPool<T, S,>
+  where T: Clone, S: PoolStorage<T,>, {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`HistoryPool::index_mut` {}", e),)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{KeyState, storage::HashStorage,};
+  use std::marker::PhantomData;
+
+  #[test]
+  fn test_undo_redo() {
+    let mut pool = HistoryPool::<i32, HashStorage<_,>,>::default();
+    let a = pool.insert(1,);
+    let b = pool.insert(2,);
+    pool.checkpoint("insert",);
+
+    pool[a] += 10;
+    pool[a] += 10;
+    pool.remove(b,);
+    let c = pool.insert(3,);
+    pool[c] = 4;
+    pool.checkpoint("edit",);
+
+    assert_eq!(pool.undo(), Some(Some("edit",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool[a], 1, "`HistoryPool::undo` did not restore a modified value",);
+    assert_eq!(pool[b], 2, "`HistoryPool::undo` did not restore a removed value",);
+    assert_eq!(pool.key_state(&c,), KeyState::Removed, "`HistoryPool::undo` kept an inserted value",);
+
+    assert_eq!(pool.redo(), Some(Some("edit",)), "`HistoryPool::redo` returned wrong checkpoint",);
+    assert_eq!(pool[a], 21, "`HistoryPool::redo` did not redo a modification",);
+    assert_eq!(pool[c], 4, "`HistoryPool::redo` did not reinsert a value under its key",);
+    assert!(!pool.contains_key(&b,), "`HistoryPool::redo` did not redo a removal",);
+
+    assert_eq!(pool.undo(), Some(Some("edit",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool.undo(), Some(Some("insert",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert!(pool.is_empty(), "`HistoryPool::undo` kept an inserted value",);
+    assert_eq!(pool.undo(), None, "`HistoryPool::undo` undid a missing checkpoint",);
+  }
+  #[test]
+  fn test_edit_clears_redo() {
+    let mut pool = HistoryPool::new();
+    let key = pool.insert(1,);
+    pool.checkpoint("insert",);
+    pool[key] = 2;
+    pool.checkpoint("set",);
+
+    pool.undo();
+    assert!(pool.can_redo(), "`HistoryPool::undo` did not allow a redo",);
+    pool[key] = 3;
+    assert!(!pool.can_redo(), "`HistoryPool::get_mut` kept the undone checkpoint",);
+    assert_eq!(pool.redo(), None, "`HistoryPool::redo` redid a discarded checkpoint",);
+
+    pool.undo();
+    assert_eq!(pool[key], 1, "`HistoryPool::undo` did not undo the new edit",);
+    assert!(!pool.remove(PoolKey(key.0, key.1, usize::MAX, PhantomData,),), "`HistoryPool::remove` removed a missing key",);
+    assert!(pool.can_redo(), "`HistoryPool::remove` recorded a failed removal",);
+    pool.remove(key,);
+    assert!(!pool.can_redo(), "`HistoryPool::remove` kept the undone checkpoint",);
+  }
+  #[test]
+  fn test_undo_past_checkpoint() {
+    let mut pool = HistoryPool::new();
+    let key = pool.insert(1,);
+    pool.checkpoint("insert",);
+    pool[key] = 2;
+    pool.checkpoint("first",);
+    //The value is saved again after the checkpoint even though it was saved before it.
+    pool[key] = 3;
+
+    assert!(pool.can_undo(), "`HistoryPool::can_undo` ignored pending changes",);
+    assert_eq!(pool.undo(), Some(None), "`HistoryPool::undo` did not undo the pending changes first",);
+    assert_eq!(pool[key], 2, "`HistoryPool::undo` undid past the checkpoint",);
+    assert_eq!(pool.undo(), Some(Some("first",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool[key], 1, "`HistoryPool::undo` did not undo the checkpoint",);
+
+    assert_eq!(pool.redo(), Some(Some("first",)), "`HistoryPool::redo` returned wrong checkpoint",);
+    assert_eq!(pool.redo(), Some(None), "`HistoryPool::redo` lost the pending changes",);
+    assert_eq!(pool[key], 3, "`HistoryPool::redo` did not redo the pending changes",);
+  }
+  #[test]
+  fn test_history_limit() {
+    let mut pool = HistoryPool::with_history_limit(2,);
+    let keys = (0..4).map(|value,| {
+      let key = pool.insert(value,);
+
+      pool.checkpoint("insert",);
+      key
+    },).collect::<Vec<_>>();
+
+    assert_eq!(pool.undo(), Some(Some("insert",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool.undo(), Some(Some("insert",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool.undo(), None, "`HistoryPool::undo` exceeded the history limit",);
+    assert!(pool.contains_key(&keys[0],) && pool.contains_key(&keys[1],), "`HistoryPool` lost the values of evicted checkpoints",);
+    assert_eq!(pool.len(), 2, "`HistoryPool::undo` undid the wrong checkpoints",);
+
+    pool.redo();
+    pool.redo();
+    pool[keys[0]] = 10;
+    pool.checkpoint("set",);
+    assert_eq!(pool.undo(), Some(Some("set",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool.undo(), Some(Some("insert",)), "`HistoryPool::undo` returned wrong checkpoint",);
+    assert_eq!(pool.undo(), None, "`HistoryPool::checkpoint` did not evict the oldest checkpoint",);
+    assert_eq!(pool.len(), 3, "`HistoryPool::undo` undid an evicted checkpoint",);
+
+    pool.set_history_limit(0,);
+    assert!(!pool.can_undo(), "`HistoryPool::set_history_limit` kept old checkpoints",);
+  }
+}
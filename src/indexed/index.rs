@@ -0,0 +1,153 @@
+//! Defines the indexes which can be registered with an [IndexedPool](super::IndexedPool).
+
+use crate::PoolKey;
+use std::{
+  any::Any,
+  hash::Hash,
+  num::NonZeroUsize,
+  marker::PhantomData,
+  collections::{HashMap, HashSet, hash_set,},
+};
+
+/// A type erased index over the values of an [IndexedPool](super::IndexedPool).
+pub(super) trait AnyIndex<T,>: Any + Send + Sync {
+  /// Returns the name the index was registered with.
+  fn name(&self,) -> &'static str;
+  /// Returns `true` if values can clash in the index.
+  fn is_unique(&self,) -> bool;
+  /// Returns the key of the value which `value` clashes with in the index.
+  fn clash(&self, value: &T,) -> Option<PoolKey<T,>>;
+  /// Adds `value` to the index under `key`.
+  fn insert(&mut self, key: PoolKey<T,>, value: &T,);
+  /// Removes `value` from the index.
+  fn remove(&mut self, key: PoolKey<T,>, value: &T,);
+  /// Returns the index as [Any] so it can be downcast.
+  fn as_any(&self,) -> &dyn Any;
+}
+
+/// A handle to a unique index registered with an [IndexedPool](super::IndexedPool).
+/// 
+/// Each value of the pool has a distinct `K`.
+pub struct UniqueIndex<T, K,> {
+  pub(super) index: usize,
+  pub(super) pool_id: NonZeroUsize,
+  pub(super) _index: PhantomData<fn(&T,) -> K>,
+}
+
+impl<T, K,> Clone for UniqueIndex<T, K,> {
+  fn clone(&self,) -> Self { *self }
+}
+
+impl<T, K,> Copy for UniqueIndex<T, K,> {}
+
+/// A handle to a multi value index registered with an [IndexedPool](super::IndexedPool).
+/// 
+/// Any number of values of the pool can share the same `K`.
+pub struct MultiIndex<T, K,> {
+  pub(super) index: usize,
+  pub(super) pool_id: NonZeroUsize,
+  pub(super) _index: PhantomData<fn(&T,) -> K>,
+}
+
+impl<T, K,> Clone for MultiIndex<T, K,> {
+  fn clone(&self,) -> Self { *self }
+}
+
+impl<T, K,> Copy for MultiIndex<T, K,> {}
+
+/// The extractor of an index.
+pub(super) type Extract<T, K,> = Box<dyn Fn(&T,) -> K + Send + Sync>;
+
+/// The id and generation of a [PoolKey].
+/// 
+/// Indexes store raw keys so they are thread safe whatever `T` is.
+pub(super) type RawKey = (usize, usize,);
+
+/// Returns the [RawKey] of `key`.
+#[inline]
+pub(super) fn raw<T,>(key: PoolKey<T,>,) -> RawKey { (key.0, key.2,) }
+
+/// Returns the [PoolKey] of `raw` in the pool `pool_id`.
+#[inline]
+pub(super) fn key<T,>(raw: RawKey, pool_id: NonZeroUsize,) -> PoolKey<T,> {
+  PoolKey(raw.0, pool_id, raw.1, PhantomData,)
+}
+
+/// The storage of a [UniqueIndex].
+pub(super) struct Unique<T, K,> {
+  pub(super) name: &'static str,
+  pub(super) pool_id: NonZeroUsize,
+  pub(super) extract: Extract<T, K,>,
+  pub(super) keys: HashMap<K, RawKey>,
+}
+
+impl<T, K,> AnyIndex<T,> for Unique<T, K,>
+  where T: 'static, K: Hash + Eq + Send + Sync + 'static, {
+  #[inline]
+  fn name(&self,) -> &'static str { self.name }
+  #[inline]
+  fn is_unique(&self,) -> bool { true }
+  #[inline]
+  fn clash(&self, value: &T,) -> Option<PoolKey<T,>> {
+    self.keys.get(&(self.extract)(value,),).map(|&raw,| key(raw, self.pool_id,),)
+  }
+  #[inline]
+  fn insert(&mut self, key: PoolKey<T,>, value: &T,) { self.keys.insert((self.extract)(value,), raw(key,),); }
+  #[inline]
+  fn remove(&mut self, _: PoolKey<T,>, value: &T,) { self.keys.remove(&(self.extract)(value,),); }
+  #[inline]
+  fn as_any(&self,) -> &dyn Any { self }
+}
+
+/// The storage of a [MultiIndex].
+pub(super) struct Multi<T, K,> {
+  pub(super) name: &'static str,
+  pub(super) extract: Extract<T, K,>,
+  pub(super) keys: HashMap<K, HashSet<RawKey>>,
+}
+
+impl<T, K,> AnyIndex<T,> for Multi<T, K,>
+  where T: 'static, K: Hash + Eq + Send + Sync + 'static, {
+  #[inline]
+  fn name(&self,) -> &'static str { self.name }
+  #[inline]
+  fn is_unique(&self,) -> bool { false }
+  #[inline]
+  fn clash(&self, _: &T,) -> Option<PoolKey<T,>> { None }
+  #[inline]
+  fn insert(&mut self, key: PoolKey<T,>, value: &T,) {
+    self.keys.entry((self.extract)(value,),).or_default().insert(raw(key,),);
+  }
+  fn remove(&mut self, key: PoolKey<T,>, value: &T,) {
+    let field = (self.extract)(value,);
+
+    if let Some(keys,) = self.keys.get_mut(&field,) {
+      keys.remove(&raw(key,),);
+      if keys.is_empty() { self.keys.remove(&field,); }
+    }
+  }
+  #[inline]
+  fn as_any(&self,) -> &dyn Any { self }
+}
+
+/// An iterator over the [PoolKey]s of the values sharing a field in a [MultiIndex].
+pub struct MultiKeys<'a, T,> {
+  pub(super) keys: Option<hash_set::Iter<'a, RawKey>>,
+  pub(super) pool_id: NonZeroUsize,
+  pub(super) _keys: PhantomData<PoolKey<T,>>,
+}
+
+impl<T,> Iterator for MultiKeys<'_, T,> {
+  type Item = PoolKey<T,>;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    let pool_id = self.pool_id;
+
+    self.keys.as_mut()?.next().map(|&raw,| key(raw, pool_id,),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) {
+    self.keys.as_ref().map_or((0, Some(0,),), Iterator::size_hint,)
+  }
+}
@@ -0,0 +1,704 @@
+//! Defines the [IndexedPool] which keeps secondary indexes over its values.
+//! 
+//! An index is registered with a name and a closure which extracts a field from each
+//! value. Unique and multi value indexes look values up by their field while ordered
+//! indexes also find values by a range of fields.
+//! 
+//! Unique indexes and unique constraints are checked before any value is inserted or
+//! released by a [ModifyGuard], so no two values of an IndexedPool ever share a field
+//! they constrain.
+//! 
+//! A [Query] combines conditions on several indexes and picks the index which narrows
+//! the search the most. The IndexedPool updates its indexes whenever values are inserted or removed
+//! and whenever a [ModifyGuard] releases a value, so the indexes are never stale.
+
+mod index;
+mod ordered;
+mod query;
+
+pub use self::{
+  index::{UniqueIndex, MultiIndex, MultiKeys,},
+  ordered::{OrderedIndex, OrderedKeys, OrderedEntries,},
+  query::{Query, QueryIter, EqIndex,},
+};
+
+use self::{index::{AnyIndex, Unique, Multi,}, ordered::Ordered,};
+use crate::{TypePool, PoolKey, PoolError, storage::{PoolStorage, DefaultStorage,},};
+use std::{
+  ops, fmt, error, thread, iter,
+  hash::Hash,
+  borrow::Borrow,
+  num::NonZeroUsize,
+  marker::PhantomData,
+};
+
+/// An error from a fallible [IndexedPool] operation.
+pub enum IndexError<T,> {
+  /// The underlying [TypePool] failed.
+  Pool(PoolError,),
+  /// The value has the same field as an existing value in a unique index or constraint.
+  Violation {
+    /// The name of the unique index or constraint.
+    constraint: &'static str,
+    /// The key of the existing value.
+    existing: PoolKey<T,>,
+  },
+}
+
+impl<T,> PartialEq for IndexError<T,> {
+  fn eq(&self, rhs: &Self,) -> bool {
+    match (self, rhs,) {
+      (IndexError::Pool(lhs,), IndexError::Pool(rhs,),) => lhs == rhs,
+      (
+        IndexError::Violation { constraint: lhs_constraint, existing: lhs_existing, },
+        IndexError::Violation { constraint: rhs_constraint, existing: rhs_existing, },
+      ) => lhs_constraint == rhs_constraint && lhs_existing == rhs_existing,
+      _ => false,
+    }
+  }
+}
+
+impl<T,> Eq for IndexError<T,> {}
+
+impl<T,> Clone for IndexError<T,> {
+  fn clone(&self,) -> Self { *self }
+}
+
+impl<T,> Copy for IndexError<T,> {}
+
+impl<T,> From<PoolError> for IndexError<T,> {
+  #[inline]
+  fn from(from: PoolError,) -> Self { IndexError::Pool(from,) }
+}
+
+impl<T,> fmt::Debug for IndexError<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    match self {
+      IndexError::Pool(e,) => fmt.debug_tuple("Pool",).field(e,).finish(),
+      IndexError::Violation { constraint, existing, } => fmt.debug_struct("Violation",)
+        .field("constraint", constraint,)
+        .field("existing", existing,)
+        .finish(),
+    }
+  }
+}
+
+impl<T,> fmt::Display for IndexError<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    match self {
+      IndexError::Pool(e,) => e.fmt(fmt,),
+      IndexError::Violation { constraint, existing, } => write!(fmt, "`{}` clashes with {:?}", constraint, existing,),
+    }
+  }
+}
+
+impl<T,> error::Error for IndexError<T,> {}
+
+/// A [TypePool] which keeps secondary indexes over its values.
+/// 
+/// The IndexedPool dereferences to its TypePool for reading; values can only be changed
+/// through the IndexedPool so that its indexes are kept up to date.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::IndexedPool;
+/// 
+/// struct User { email: String, country: &'static str, }
+/// 
+/// let mut pool = IndexedPool::new();
+/// let by_email = pool.add_unique_index("email", |user: &User,| user.email.clone(),).unwrap();
+/// let by_country = pool.add_multi_index("country", |user: &User,| user.country,).unwrap();
+/// 
+/// let key = pool.insert(User { email: "a@example.com".into(), country: "au", },);
+/// pool.insert(User { email: "b@example.com".into(), country: "au", },);
+/// 
+/// assert_eq!(pool.find_unique(by_email, "a@example.com",), Some(key));
+/// assert_eq!(pool.find_multi(by_country, &"au",).count(), 2);
+/// 
+/// pool.modify(key,).unwrap().country = "nz";
+/// assert_eq!(pool.find_multi(by_country, &"au",).count(), 1);
+/// ```
+pub struct IndexedPool<T, S = DefaultStorage<T,>,> {
+  pool: TypePool<T, S,>,
+  indexes: Vec<Box<dyn AnyIndex<T,>>>,
+}
+
+impl<T,> IndexedPool<T,>
+  where T: 'static, {
+  /// Returns a new empty IndexedPool.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<T, S,> IndexedPool<T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  /// Returns the TypePool of this IndexedPool, discarding its indexes.
+  #[inline]
+  pub fn into_inner(self,) -> TypePool<T, S,> { self.pool }
+  /// Registers `index` and adds the existing values to it.
+  fn add_index(&mut self, mut index: Box<dyn AnyIndex<T,>>,) -> Result<usize, IndexError<T,>> {
+    for (key, value,) in self.pool.iter() {
+      if let Some(existing,) = index.clash(value,) {
+        return Err(IndexError::Violation { constraint: index.name(), existing, })
+      }
+
+      index.insert(key, value,);
+    }
+
+    self.indexes.push(index,);
+    Ok(self.indexes.len() - 1)
+  }
+  /// Registers a unique index of the field returned by `extract`.
+  /// 
+  /// Fails if any existing values share a field.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the index.  
+  /// extract --- Returns the indexed field of a value.  
+  pub fn add_unique_index<K, F,>(&mut self, name: &'static str, extract: F,) -> Result<UniqueIndex<T, K,>, IndexError<T,>>
+    where K: Hash + Eq + Send + Sync + 'static, F: Fn(&T,) -> K + Send + Sync + 'static, {
+    let index = self.add_index(Box::new(Unique { name, pool_id: self.pool.pool_id, extract: Box::new(extract,), keys: Default::default(), },),)?;
+
+    Ok(UniqueIndex { index, pool_id: self.pool.pool_id, _index: PhantomData, })
+  }
+  /// Registers a multi value index of the field returned by `extract`.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the index.  
+  /// extract --- Returns the indexed field of a value.  
+  pub fn add_multi_index<K, F,>(&mut self, name: &'static str, extract: F,) -> Result<MultiIndex<T, K,>, IndexError<T,>>
+    where K: Hash + Eq + Send + Sync + 'static, F: Fn(&T,) -> K + Send + Sync + 'static, {
+    let index = self.add_index(Box::new(Multi { name, extract: Box::new(extract,), keys: Default::default(), },),)?;
+
+    Ok(MultiIndex { index, pool_id: self.pool.pool_id, _index: PhantomData, })
+  }
+  /// Registers a constraint which prevents two values having the same field returned by
+  /// `extract`.
+  /// 
+  /// Fails if any existing values share a field. Returning a tuple from `extract`
+  /// constrains the combination of several fields.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the constraint.  
+  /// extract --- Returns the constrained field of a value.  
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{IndexedPool, IndexError,};
+  /// 
+  /// struct Account { tenant: u32, name: &'static str, }
+  /// 
+  /// let mut pool = IndexedPool::new();
+  /// pool.add_unique_constraint("tenant_name", |account: &Account,| (account.tenant, account.name,),).unwrap();
+  /// 
+  /// let key = pool.insert(Account { tenant: 1, name: "admin", },);
+  /// pool.insert(Account { tenant: 2, name: "admin", },);
+  /// 
+  /// let clash = pool.try_insert(Account { tenant: 1, name: "admin", },).err().map(|(e, _,),| e,);
+  /// assert_eq!(clash, Some(IndexError::Violation { constraint: "tenant_name", existing: key, }));
+  /// ```
+  pub fn add_unique_constraint<K, F,>(&mut self, name: &'static str, extract: F,) -> Result<(), IndexError<T,>>
+    where K: Hash + Eq + Send + Sync + 'static, F: Fn(&T,) -> K + Send + Sync + 'static, {
+    //A constraint is a unique index which cannot be searched.
+    self.add_index(Box::new(Unique { name, pool_id: self.pool.pool_id, extract: Box::new(extract,), keys: Default::default(), },),)
+    .map(|_,| (),)
+  }
+  /// Registers an ordered index of the field returned by `extract`.
+  /// 
+  /// # Params
+  /// 
+  /// name --- The name of the index.  
+  /// extract --- Returns the indexed field of a value.  
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::IndexedPool;
+  /// 
+  /// let mut pool = IndexedPool::new();
+  /// let by_price = pool.add_ordered_index("price", |price: &u32,| *price,).unwrap();
+  /// 
+  /// for price in [30, 10, 50, 20, 40] { pool.insert(price,); }
+  /// 
+  /// let prices = pool.range(by_price, 15..45,).with_values().map(|(_, price,),| *price,).collect::<Vec<_>>();
+  /// assert_eq!(prices, [20, 30, 40]);
+  /// assert_eq!(pool.top_k(by_price, 2,).map(|key,| pool[key],).collect::<Vec<_>>(), [50, 40]);
+  /// ```
+  pub fn add_ordered_index<K, F,>(&mut self, name: &'static str, extract: F,) -> Result<OrderedIndex<T, K,>, IndexError<T,>>
+    where K: Ord + Send + Sync + 'static, F: Fn(&T,) -> K + Send + Sync + 'static, {
+    let index = self.add_index(Box::new(Ordered { name, extract: Box::new(extract,), keys: Default::default(), },),)?;
+
+    Ok(OrderedIndex { index, pool_id: self.pool.pool_id, _index: PhantomData, })
+  }
+  /// Returns the index at `index` as an `I`.
+  /// 
+  /// # Panics
+  /// 
+  /// If the index was registered with another IndexedPool.
+  fn index<I,>(&self, index: usize, pool_id: NonZeroUsize,) -> &I
+    where I: 'static, {
+    assert!(pool_id == self.pool.pool_id, "`IndexedPool` index was registered with another pool",);
+
+    self.indexes[index].as_any().downcast_ref().expect("`IndexedPool` index has the wrong type",)
+  }
+  /// Returns the key of the first value `value` clashes with in a unique index or
+  /// constraint.
+  fn check_value(&self, value: &T,) -> Result<(), IndexError<T,>> {
+    match self.indexes.iter().find_map(|index,| index.clash(value,).map(|existing,| (index.name(), existing,),),) {
+      Some((constraint, existing,),) => Err(IndexError::Violation { constraint, existing, }),
+      None => Ok(()),
+    }
+  }
+  /// Inserts `value` into the IndexedPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the IndexedPool is full or `value` clashes with another value in a unique index
+  /// or constraint.
+  pub fn insert(&mut self, value: T,) -> PoolKey<T,> {
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("`IndexedPool::insert` {}", e),)
+  }
+  /// Attempts to insert `value` into the IndexedPool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the IndexedPool is full or
+  /// `value` clashes with another value in a unique index or constraint.
+  pub fn try_insert(&mut self, value: T,) -> Result<PoolKey<T,>, (IndexError<T,>, T,)> {
+    if let Err(e,) = self.check_value(&value,) { return Err((e, value,)) }
+
+    let key = self.pool.try_insert(value,).map_err(|(e, value,),| (e.into(), value,),)?;
+    let value = &self.pool[key];
+
+    for index in self.indexes.iter_mut() { index.insert(key, value,); }
+    Ok(key)
+  }
+  /// Removes the value mapped too `key`.
+  /// 
+  /// Returns `None` if the value has already been removed.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is not owned by this pool.
+  pub fn remove(&mut self, key: PoolKey<T,>,) -> Option<T> {
+    assert!(self.pool.owns_key(&key,), "`IndexedPool::remove` `key` must be owned by this pool",);
+
+    self.try_remove(key,).ok()
+  }
+  /// Attempts to remove the value mapped too `key`.
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<T, PoolError> {
+    let value = self.pool.try_remove(key,)?;
+
+    for index in self.indexes.iter_mut() { index.remove(key, &value,); }
+    Ok(value)
+  }
+  /// Returns a guard which allows the value mapped too `key` to be changed.
+  /// 
+  /// The indexes are updated when the guard is released. If the changes clash with another
+  /// value the value is removed; use [IndexedPool::modify_with_rollback] to restore it
+  /// instead.
+  pub fn modify(&mut self, key: PoolKey<T,>,) -> Result<ModifyGuard<'_, T, S,>, PoolError> {
+    self.borrow_value(key, None,)
+  }
+  /// Returns a guard which allows the value mapped too `key` to be changed.
+  /// 
+  /// The indexes are updated when the guard is released. If the changes clash with another
+  /// value the value is restored; it is only cloned if a unique index or constraint is
+  /// registered, as otherwise the changes cannot clash.
+  pub fn modify_with_rollback(&mut self, key: PoolKey<T,>,) -> Result<ModifyGuard<'_, T, S,>, PoolError>
+    where T: Clone, {
+    let value = self.pool.get(key,)?;
+    let original = if self.indexes.iter().any(|index,| index.is_unique(),) { Some(value.clone(),) }
+      else { None };
+
+    self.borrow_value(key, original,)
+  }
+  /// Removes the value mapped too `key` from the indexes and returns a guard over it.
+  fn borrow_value(&mut self, key: PoolKey<T,>, original: Option<T>,) -> Result<ModifyGuard<'_, T, S,>, PoolError> {
+    let value = self.pool.get(key,)?;
+
+    for index in self.indexes.iter_mut() { index.remove(key, value,); }
+    Ok(ModifyGuard { pool: self, key, original, committed: false, })
+  }
+  /// Returns the key of the value whose field is `field` in `index`.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  pub fn find_unique<K, Q,>(&self, index: UniqueIndex<T, K,>, field: &Q,) -> Option<PoolKey<T,>>
+    where K: Borrow<Q> + Hash + Eq + 'static, Q: Hash + Eq + ?Sized, {
+    self.index::<Unique<T, K,>>(index.index, index.pool_id,).keys.get(field,).map(|&raw,| index::key(raw, index.pool_id,),)
+  }
+  /// Returns the keys of the values whose field is `field` in `index`.
+  /// 
+  /// The order of the keys is unspecified.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  pub fn find_multi<K, Q,>(&self, index: MultiIndex<T, K,>, field: &Q,) -> MultiKeys<'_, T,>
+    where K: Borrow<Q> + Hash + Eq + 'static, Q: Hash + Eq + ?Sized, {
+    let keys = self.index::<Multi<T, K,>>(index.index, index.pool_id,).keys.get(field,);
+
+    MultiKeys { keys: keys.map(|keys,| keys.iter(),), pool_id: index.pool_id, _keys: PhantomData, }
+  }
+  /// Returns a [Query] which matches every value of this IndexedPool.
+  #[inline]
+  pub fn query(&self,) -> Query<'_, T, S,> { Query::new(self,) }
+  /// Returns the keys of every value in the order of their fields in `index`.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  #[inline]
+  pub fn ordered<K,>(&self, index: OrderedIndex<T, K,>,) -> OrderedKeys<'_, T, K, S,>
+    where K: Ord + 'static, {
+    self.range(index, ..,)
+  }
+  /// Returns the keys of the values whose fields are in `range` in the order of their
+  /// fields in `index`.
+  /// 
+  /// An empty or inverted `range` returns no keys.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  pub fn range<K, Q, R,>(&self, index: OrderedIndex<T, K,>, range: R,) -> OrderedKeys<'_, T, K, S,>
+    where K: Borrow<Q> + Ord + 'static, Q: Ord + ?Sized, R: ops::RangeBounds<Q>, {
+    let keys = &self.index::<Ordered<T, K,>>(index.index, index.pool_id,).keys;
+    let range = if ordered::is_valid_range(range.start_bound(), range.end_bound(),) { keys.range(range,) }
+      else { Default::default() };
+
+    OrderedKeys::new(range, &self.pool,)
+  }
+  /// Returns the key of the value with the smallest field in `index`.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  #[inline]
+  pub fn first<K,>(&self, index: OrderedIndex<T, K,>,) -> Option<PoolKey<T,>>
+    where K: Ord + 'static, {
+    self.ordered(index,).next()
+  }
+  /// Returns the key of the value with the largest field in `index`.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  #[inline]
+  pub fn last<K,>(&self, index: OrderedIndex<T, K,>,) -> Option<PoolKey<T,>>
+    where K: Ord + 'static, {
+    self.ordered(index,).next_back()
+  }
+  /// Returns the keys of the `k` values with the largest fields in `index`, largest first.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  #[inline]
+  pub fn top_k<K,>(&self, index: OrderedIndex<T, K,>, k: usize,) -> iter::Take<iter::Rev<OrderedKeys<'_, T, K, S,>>>
+    where K: Ord + 'static, {
+    self.ordered(index,).rev().take(k,)
+  }
+}
+
+impl<T, S,> Default for IndexedPool<T, S,>
+  where S: PoolStorage<T,>, {
+  #[inline]
+  fn default() -> Self { Self { pool: TypePool::default(), indexes: Vec::new(), } }
+}
+
+impl<T, S,> From<TypePool<T, S,>> for IndexedPool<T, S,> {
+  #[inline]
+  fn from(pool: TypePool<T, S,>,) -> Self { Self { pool, indexes: Vec::new(), } }
+}
+
+impl<T, S,> ops::Deref for IndexedPool<T, S,> {
+  type Target = TypePool<T, S,>;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { &self.pool }
+}
+
+/// A mutable borrow of a value in an [IndexedPool].
+/// 
+/// The value is removed from the indexes while it is borrowed and added back when the
+/// guard is released.
+/// 
+/// # Panics
+/// 
+/// Dropping the guard panics if the value clashes with another value in a unique index
+/// or constraint, after restoring the value it had when the guard was created if it was
+/// created by [IndexedPool::modify_with_rollback] or removing the value otherwise; use
+/// [ModifyGuard::try_commit] to recover from a clash.
+pub struct ModifyGuard<'a, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  pool: &'a mut IndexedPool<T, S,>,
+  key: PoolKey<T,>,
+  /// The value before it was borrowed if it can be restored.
+  original: Option<T>,
+  /// Whether the value has been added back to the indexes.
+  committed: bool,
+}
+
+impl<T, S,> ModifyGuard<'_, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  /// Returns the [PoolKey] of the value.
+  #[inline]
+  pub fn key(&self,) -> PoolKey<T,> { self.key }
+  /// Adds the value back to the indexes without checking for clashes.
+  fn index(&mut self,) {
+    let value = &self.pool.pool[self.key];
+
+    for index in self.pool.indexes.iter_mut() { index.insert(self.key, value,); }
+  }
+  /// Adds the value back to the indexes.
+  fn reindex(&mut self,) -> Result<(), IndexError<T,>> {
+    self.pool.check_value(&self.pool.pool[self.key],)?;
+    self.index();
+
+    Ok(())
+  }
+  /// Releases the value and updates the indexes.
+  /// 
+  /// Returns the guard if the value clashes with another value in a unique index or
+  /// constraint.
+  pub fn try_commit(mut self,) -> Result<(), (IndexError<T,>, Self,)> {
+    match self.reindex() {
+      Ok(()) => { self.committed = true; Ok(()) },
+      Err(e,) => Err((e, self,)),
+    }
+  }
+}
+
+impl<T, S,> ops::Deref for ModifyGuard<'_, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  type Target = T;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { &self.pool.pool[self.key] }
+}
+
+impl<T, S,> ops::DerefMut for ModifyGuard<'_, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  #[inline]
+  fn deref_mut(&mut self,) -> &mut Self::Target { &mut self.pool.pool[self.key] }
+}
+
+impl<T, S,> Drop for ModifyGuard<'_, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  fn drop(&mut self,) {
+    if self.committed { return }
+
+    if let Err(e,) = self.reindex() {
+      match self.original.take() {
+        Some(original,) => {
+          //The original value fitted the indexes before it was borrowed so it still fits.
+          self.pool.pool[self.key] = original;
+          self.index();
+        },
+        //The value is not in the indexes so it is removed to keep them consistent.
+        None => { self.pool.pool.remove(self.key,); },
+      }
+      if !thread::panicking() { panic!("`ModifyGuard::drop` {}", e) }
+    }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[derive(PartialEq, Eq, Clone, Debug,)]
+  struct User { email: String, country: u8, }
+
+  #[derive(PartialEq, Eq, Debug,)]
+  struct Item(u8,);
+
+  impl User {
+    fn new(email: &str, country: u8,) -> Self { Self { email: email.into(), country, } }
+  }
+
+  #[test]
+  fn test_indexed_pool() {
+    let mut pool = IndexedPool::new();
+    let by_email = pool.add_unique_index("email", |user: &User,| user.email.clone(),).unwrap();
+    let by_country = pool.add_multi_index("country", |user: &User,| user.country,).unwrap();
+    let a = pool.insert(User::new("a", 1,),);
+    let b = pool.insert(User::new("b", 1,),);
+    let c = pool.insert(User::new("c", 2,),);
+
+    assert_eq!(pool.find_unique(by_email, "b",), Some(b), "`IndexedPool::find_unique` returned wrong key",);
+    let mut keys = pool.find_multi(by_country, &1,).collect::<Vec<_>>();
+    keys.sort_unstable_by_key(|key,| key.0,);
+    assert_eq!(keys, [a, b], "`IndexedPool::find_multi` returned wrong keys",);
+
+    let clash = pool.try_insert(User::new("a", 3,),).err().map(|(e, _,),| e,);
+    assert_eq!(clash, Some(IndexError::Violation { constraint: "email", existing: a, }), "`IndexedPool::try_insert` accepted a duplicate",);
+
+    pool.remove(a,);
+    assert_eq!(pool.find_unique(by_email, "a",), None, "`IndexedPool::remove` did not update the unique index",);
+    assert_eq!(pool.find_multi(by_country, &1,).collect::<Vec<_>>(), [b], "`IndexedPool::remove` did not update the multi index",);
+
+    pool.modify(c,).unwrap().country = 1;
+    assert_eq!(pool.find_multi(by_country, &2,).count(), 0, "`ModifyGuard` did not update the multi index",);
+    assert_eq!(pool.find_multi(by_country, &1,).count(), 2, "`ModifyGuard` did not update the multi index",);
+
+    let mut guard = pool.modify(c,).unwrap();
+    guard.email = "b".into();
+    let (e, mut guard,) = guard.try_commit().err().unwrap();
+    assert_eq!(e, IndexError::Violation { constraint: "email", existing: b, }, "`ModifyGuard::try_commit` accepted a duplicate",);
+    guard.email = "d".into();
+    assert!(guard.try_commit().is_ok(), "`ModifyGuard::try_commit` rejected a unique value",);
+    assert_eq!(pool.find_unique(by_email, "d",), Some(c), "`ModifyGuard::try_commit` did not update the unique index",);
+  }
+  #[test]
+  fn test_ordered_index() {
+    let mut pool = IndexedPool::new();
+    let by_price = pool.add_ordered_index("price", |&(_, price,): &(char, u32,),| price,).unwrap();
+    let a = pool.insert(('a', 30,),);
+    let b = pool.insert(('b', 10,),);
+    let c = pool.insert(('c', 20,),);
+    let d = pool.insert(('d', 20,),);
+
+    assert_eq!(pool.ordered(by_price,).collect::<Vec<_>>(), [b, c, d, a], "`IndexedPool::ordered` returned wrong order",);
+    assert_eq!(pool.range(by_price, 15..=20,).collect::<Vec<_>>(), [c, d], "`IndexedPool::range` returned wrong keys",);
+    assert_eq!(pool.range(by_price, 20..,).with_values().map(|(_, &(name, _,),),| name,).collect::<String>(), "cda", "`OrderedKeys::with_values` returned wrong values",);
+    assert_eq!((pool.first(by_price,), pool.last(by_price,),), (Some(b,), Some(a,),), "`IndexedPool::first` or `IndexedPool::last` is wrong",);
+    assert_eq!(pool.top_k(by_price, 2,).collect::<Vec<_>>(), [a, d], "`IndexedPool::top_k` returned wrong keys",);
+    let (high, low,) = (30, 10,);
+    assert_eq!(pool.range(by_price, high..low,).count(), 0, "`IndexedPool::range` returned keys for an inverted range",);
+    assert_eq!(pool.range(by_price, (ops::Bound::Excluded(20,), ops::Bound::Excluded(20,),),).count(), 0, "`IndexedPool::range` returned keys for an empty range",);
+    assert_eq!(pool.range(by_price, 20..=20,).collect::<Vec<_>>(), [c, d], "`IndexedPool::range` rejected a single field",);
+
+    pool.modify(b,).unwrap().1 = 40;
+    pool.remove(a,);
+    assert_eq!(pool.ordered(by_price,).collect::<Vec<_>>(), [c, d, b], "`IndexedPool` did not update the ordered index",);
+    assert_eq!(pool.range(by_price, 25..35,).count(), 0, "`IndexedPool::remove` did not update the ordered index",);
+  }
+  #[test]
+  fn test_unique_constraint() {
+    let mut pool = IndexedPool::new();
+    pool.add_unique_constraint("email_country", |user: &User,| (user.email.clone(), user.country,),).unwrap();
+    let a = pool.insert(User::new("a", 1,),);
+    let b = pool.insert(User::new("a", 2,),);
+
+    let clash = pool.try_insert(User::new("a", 2,),).err().map(|(e, _,),| e,);
+    assert_eq!(clash, Some(IndexError::Violation { constraint: "email_country", existing: b, }), "`IndexedPool::try_insert` broke a constraint",);
+
+    let mut guard = pool.modify(b,).unwrap();
+    guard.country = 1;
+    let (e, mut guard,) = guard.try_commit().err().unwrap();
+    assert_eq!(e, IndexError::Violation { constraint: "email_country", existing: a, }, "`ModifyGuard::try_commit` broke a constraint",);
+    guard.country = 3;
+    drop(guard,);
+
+    pool.remove(a,);
+    assert!(pool.try_insert(User::new("a", 1,),).is_ok(), "`IndexedPool::remove` did not release the constraint",);
+    assert_eq!(pool.len(), 2, "`IndexedPool::try_insert` lost a value",);
+  }
+  #[test]
+  #[should_panic(expected = "`ModifyGuard::drop` `email` clashes with",)]
+  fn test_modify_guard_panics() {
+    let mut pool = IndexedPool::new();
+    pool.add_unique_constraint("email", |user: &User,| user.email.clone(),).unwrap();
+    pool.insert(User::new("a", 1,),);
+    let b = pool.insert(User::new("b", 1,),);
+
+    pool.modify(b,).unwrap().email = "a".into();
+  }
+  #[test]
+  fn test_modify_guard_restores() {
+    let mut pool = IndexedPool::new();
+    let by_email = pool.add_unique_index("email", |user: &User,| user.email.clone(),).unwrap();
+    let by_country = pool.add_multi_index("country", |user: &User,| user.country,).unwrap();
+    let a = pool.insert(User::new("a", 1,),);
+    let b = pool.insert(User::new("b", 1,),);
+
+    let clash = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+      let mut guard = pool.modify_with_rollback(b,).unwrap();
+      guard.email = "a".into();
+      guard.country = 2;
+    },),);
+    assert!(clash.is_err(), "`ModifyGuard::drop` accepted a duplicate",);
+
+    assert_eq!(pool.get(b,), Ok(&User::new("b", 1,),), "`ModifyGuard::drop` did not restore the value",);
+    assert_eq!(pool.find_unique(by_email, "a",), Some(a), "`ModifyGuard::drop` broke the unique index",);
+    assert_eq!(pool.find_unique(by_email, "b",), Some(b), "`ModifyGuard::drop` did not reindex the value",);
+    assert_eq!(pool.find_multi(by_country, &1,).count(), 2, "`ModifyGuard::drop` did not reindex the value",);
+    assert_eq!(pool.find_multi(by_country, &2,).count(), 0, "`ModifyGuard::drop` indexed the clashing value",);
+  }
+  #[test]
+  fn test_modify_guard_removes() {
+    let mut pool = IndexedPool::new();
+    let by_id = pool.add_unique_index("id", |item: &Item,| item.0,).unwrap();
+    let a = pool.insert(Item(1,),);
+    let b = pool.insert(Item(2,),);
+
+    pool.modify(b,).unwrap().0 = 3;
+    assert_eq!(pool.find_unique(by_id, &3,), Some(b), "`ModifyGuard::drop` did not reindex the value",);
+
+    let clash = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pool.modify(b,).unwrap().0 = 1,),);
+    assert!(clash.is_err(), "`ModifyGuard::drop` accepted a duplicate",);
+
+    assert_eq!(pool.get(b,), Err(PoolError::StaleKey,), "`ModifyGuard::drop` did not remove the value",);
+    assert_eq!(pool.find_unique(by_id, &1,), Some(a), "`ModifyGuard::drop` broke the unique index",);
+    assert_eq!(pool.find_unique(by_id, &3,), None, "`ModifyGuard::drop` left the value indexed",);
+  }
+  #[test]
+  fn test_query() {
+    let mut pool = IndexedPool::new();
+    let by_email = pool.add_unique_index("email", |user: &User,| user.email.clone(),).unwrap();
+    let by_country = pool.add_multi_index("country", |user: &User,| user.country,).unwrap();
+    let by_length = pool.add_ordered_index("length", |user: &User,| user.email.len(),).unwrap();
+    let mut keys = Vec::new();
+
+    for email in ["a", "bb", "ccc", "dddd", "eeeee", "ffffff"] {
+      keys.push(pool.insert(User::new(email, email.len() as u8 % 2,),),);
+    }
+
+    let query = pool.query().where_eq(by_country, 0,).where_eq(by_email, "dddd".to_string(),);
+    assert_eq!(query.explain(), "scan `email` unique eq (1 candidate), intersect `country` multi eq", "`Query::explain` chose the wrong index",);
+    assert_eq!(query.into_iter().map(|(key, _,),| key,).collect::<Vec<_>>(), [keys[3]], "`Query` returned wrong keys",);
+
+    let query = pool.query().where_range(by_length, 2..6,).where_eq(by_country, 1,).filter(|user,| user.email != "ccc",);
+    assert_eq!(query.explain(), "scan `country` multi eq (3 candidates), intersect `length` range, 1 filter", "`Query::explain` chose the wrong index",);
+    assert_eq!(query.into_iter().map(|(key, _,),| key,).collect::<Vec<_>>(), [keys[4]], "`Query` returned wrong keys",);
+
+    let query = pool.query().where_eq(by_length, 3,).where_eq(by_country, 0,);
+    assert_eq!(query.into_iter().count(), 0, "`Query` ignored a condition",);
+
+    let query = pool.query().where_range(by_length, ..,).where_range(by_length, 2..4,);
+    assert_eq!(query.explain(), "scan `length` range (2 candidates), intersect `length` range", "`Query::explain` chose the wrong range",);
+    assert_eq!(query.into_iter().map(|(key, _,),| key,).collect::<Vec<_>>(), [keys[1], keys[2]], "`Query` did not intersect the ranges",);
+
+    let (high, low,) = (5, 2,);
+    let query = pool.query().where_range(by_length, high..low,);
+    assert_eq!(query.explain(), "scan `length` range (0 candidates)", "`Query::explain` counted an inverted range",);
+    assert_eq!(query.into_iter().count(), 0, "`Query` matched an inverted range",);
+
+    let query = pool.query().where_eq(by_country, 1,).where_range(by_length, high..low,);
+    assert_eq!(query.into_iter().count(), 0, "`Query` matched an inverted range",);
+
+    let query = pool.query().filter(|user,| user.country == 0,);
+    assert_eq!(query.explain(), "scan pool (6 candidates), 1 filter", "`Query::explain` used an index",);
+    assert_eq!(query.into_iter().count(), 3, "`Query::filter` returned wrong values",);
+  }
+  #[test]
+  fn test_add_index() {
+    let mut pool = TypePool::new();
+    let a = pool.insert(User::new("a", 1,),);
+    pool.insert(User::new("a", 1,),);
+
+    let mut pool = IndexedPool::from(pool,);
+    let by_country = pool.add_multi_index("country", |user: &User,| user.country,).unwrap();
+    assert_eq!(pool.find_multi(by_country, &1,).count(), 2, "`IndexedPool::add_multi_index` missed existing values",);
+    assert_eq!(pool.add_unique_index("email", |user: &User,| user.email.clone(),).err(), Some(IndexError::Violation { constraint: "email", existing: a, }), "`IndexedPool::add_unique_index` accepted duplicates",);
+  }
+}
@@ -0,0 +1,148 @@
+//! Defines the ordered indexes which can be registered with an
+//! [IndexedPool](super::IndexedPool).
+
+use super::index::{AnyIndex, Extract, RawKey, raw, key,};
+use crate::{TypePool, PoolKey, storage::PoolStorage,};
+use std::{
+  any::Any,
+  ops::Bound,
+  iter::FlatMap,
+  num::NonZeroUsize,
+  marker::PhantomData,
+  collections::{BTreeMap, BTreeSet, btree_map, btree_set,},
+};
+
+/// A handle to an ordered index registered with an [IndexedPool](super::IndexedPool).
+/// 
+/// Any number of values of the pool can share the same `K`; values with the same `K`
+/// are ordered by their [PoolKey].
+pub struct OrderedIndex<T, K,> {
+  pub(super) index: usize,
+  pub(super) pool_id: NonZeroUsize,
+  pub(super) _index: PhantomData<fn(&T,) -> K>,
+}
+
+impl<T, K,> Clone for OrderedIndex<T, K,> {
+  fn clone(&self,) -> Self { *self }
+}
+
+impl<T, K,> Copy for OrderedIndex<T, K,> {}
+
+/// The storage of an [OrderedIndex].
+pub(super) struct Ordered<T, K,> {
+  pub(super) name: &'static str,
+  pub(super) extract: Extract<T, K,>,
+  pub(super) keys: BTreeMap<K, BTreeSet<RawKey>>,
+}
+
+impl<T, K,> AnyIndex<T,> for Ordered<T, K,>
+  where T: 'static, K: Ord + Send + Sync + 'static, {
+  #[inline]
+  fn name(&self,) -> &'static str { self.name }
+  #[inline]
+  fn is_unique(&self,) -> bool { false }
+  #[inline]
+  fn clash(&self, _: &T,) -> Option<PoolKey<T,>> { None }
+  #[inline]
+  fn insert(&mut self, key: PoolKey<T,>, value: &T,) {
+    self.keys.entry((self.extract)(value,),).or_default().insert(raw(key,),);
+  }
+  fn remove(&mut self, key: PoolKey<T,>, value: &T,) {
+    let field = (self.extract)(value,);
+
+    if let Some(keys,) = self.keys.get_mut(&field,) {
+      keys.remove(&raw(key,),);
+      if keys.is_empty() { self.keys.remove(&field,); }
+    }
+  }
+  #[inline]
+  fn as_any(&self,) -> &dyn Any { self }
+}
+
+/// Returns `true` if `start` and `end` can be passed to `BTreeMap::range`, which panics
+/// if `start` is after `end` or both exclude the same field.
+pub(super) fn is_valid_range<Q,>(start: Bound<&Q,>, end: Bound<&Q,>,) -> bool
+  where Q: Ord + ?Sized, {
+  match (start, end,) {
+    (Bound::Excluded(start,), Bound::Excluded(end,),) => start < end,
+    (Bound::Included(start,) | Bound::Excluded(start,), Bound::Included(end,) | Bound::Excluded(end,),) => start <= end,
+    _ => true,
+  }
+}
+
+/// The iterator over the raw keys of an [OrderedIndex].
+type RawKeys<'a, K,> = FlatMap<
+  btree_map::Range<'a, K, BTreeSet<RawKey>>,
+  btree_set::Iter<'a, RawKey>,
+  fn((&'a K, &'a BTreeSet<RawKey>,),) -> btree_set::Iter<'a, RawKey>,
+>;
+
+/// Returns an iterator over the raw keys of a field.
+#[inline]
+fn field_keys<'a, K,>((_, keys,): (&'a K, &'a BTreeSet<RawKey>,),) -> btree_set::Iter<'a, RawKey> { keys.iter() }
+
+/// An iterator over the [PoolKey]s of an [OrderedIndex] in the order of their fields.
+pub struct OrderedKeys<'a, T, K, S,> {
+  keys: RawKeys<'a, K,>,
+  pool: &'a TypePool<T, S,>,
+}
+
+impl<'a, T, K, S,> OrderedKeys<'a, T, K, S,> {
+  /// Returns an iterator over the fields of `index` in `range`.
+  pub(super) fn new(range: btree_map::Range<'a, K, BTreeSet<RawKey>>, pool: &'a TypePool<T, S,>,) -> Self {
+    Self { keys: range.flat_map(field_keys as fn(_,) -> _,), pool, }
+  }
+  /// Returns an iterator over the [PoolKey]s and values instead.
+  #[inline]
+  pub fn with_values(self,) -> OrderedEntries<'a, T, K, S,> { OrderedEntries(self,) }
+}
+
+impl<T, K, S,> Iterator for OrderedKeys<'_, T, K, S,> {
+  type Item = PoolKey<T,>;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    let pool_id = self.pool.pool_id;
+
+    self.keys.next().map(|&raw,| key(raw, pool_id,),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.keys.size_hint() }
+}
+
+impl<T, K, S,> DoubleEndedIterator for OrderedKeys<'_, T, K, S,> {
+  #[inline]
+  fn next_back(&mut self,) -> Option<Self::Item> {
+    let pool_id = self.pool.pool_id;
+
+    self.keys.next_back().map(|&raw,| key(raw, pool_id,),)
+  }
+}
+
+/// An iterator over the [PoolKey]s and values of an [OrderedIndex] in the order of their
+/// fields.
+pub struct OrderedEntries<'a, T, K, S,>(OrderedKeys<'a, T, K, S,>,);
+
+impl<'a, T, K, S,> Iterator for OrderedEntries<'a, T, K, S,>
+  where S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, &'a T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    let pool = self.0.pool;
+
+    self.0.next().map(|key,| (key, &pool[key],),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.0.size_hint() }
+}
+
+impl<T, K, S,> DoubleEndedIterator for OrderedEntries<'_, T, K, S,>
+  where S: PoolStorage<T,>, {
+  #[inline]
+  fn next_back(&mut self,) -> Option<Self::Item> {
+    let pool = self.0.pool;
+
+    self.0.next_back().map(|key,| (key, &pool[key],),)
+  }
+}
@@ -0,0 +1,325 @@
+//! Defines the [Query] builder which finds values of an [IndexedPool] using its indexes.
+
+use super::{
+  IndexedPool, UniqueIndex, MultiIndex, OrderedIndex,
+  index::{Unique, Multi, RawKey, raw, key,},
+  ordered::{Ordered, is_valid_range,},
+};
+use crate::{PoolKey, storage::PoolStorage,};
+use std::{
+  fmt,
+  hash::Hash,
+  ops::{Bound, RangeBounds,},
+  num::NonZeroUsize,
+};
+
+/// A condition on an indexed field of a [Query].
+pub(crate) trait Condition<'a, T,> {
+  /// Returns a description of the condition for [Query::explain].
+  fn describe(&self,) -> String;
+  /// Returns the number of values the index has which meet the condition, counting no
+  /// further than `limit`.
+  fn estimate(&self, limit: usize,) -> usize;
+  /// Returns `true` if [Condition::estimate] walks a range of fields.
+  #[inline]
+  fn is_range(&self,) -> bool { false }
+  /// Returns the keys of the values which meet the condition.
+  fn keys(self: Box<Self>,) -> Box<dyn Iterator<Item = PoolKey<T,>> + 'a>;
+  /// Returns `true` if the index maps the field of `value` to `raw` and the field meets
+  /// the condition.
+  fn contains(&self, raw: &RawKey, value: &T,) -> bool;
+}
+
+/// An index which can be searched for values whose field equals a `K`.
+pub trait EqIndex<T, K,>: Copy {
+  /// Returns the condition that the field of the index equals `field`.
+  /// 
+  /// The condition cannot be named outside of this crate, which seals this trait.
+  #[doc(hidden)]
+  #[allow(private_interfaces,)]
+  fn eq_condition<'a, S,>(self, pool: &'a IndexedPool<T, S,>, field: K,) -> Box<dyn Condition<'a, T,> + 'a>
+    where T: 'static, S: PoolStorage<T,>;
+}
+
+/// Returns the [PoolKey]s of `keys`.
+fn pool_keys<'a, T, I,>(keys: I, pool_id: NonZeroUsize,) -> Box<dyn Iterator<Item = PoolKey<T,>> + 'a>
+  where T: 'a, I: Iterator<Item = &'a RawKey> + 'a, {
+  Box::new(keys.map(move |&raw,| key(raw, pool_id,),),)
+}
+
+/// A condition that the field of a [UniqueIndex] equals `field`.
+struct UniqueEq<'a, T, K,> {
+  index: &'a Unique<T, K,>,
+  field: K,
+}
+
+impl<'a, T, K,> Condition<'a, T,> for UniqueEq<'a, T, K,>
+  where T: 'static, K: Hash + Eq, {
+  fn describe(&self,) -> String { format!("`{}` unique eq", self.index.name,) }
+  fn estimate(&self, _: usize,) -> usize { self.index.keys.contains_key(&self.field,) as usize }
+  fn keys(self: Box<Self>,) -> Box<dyn Iterator<Item = PoolKey<T,>> + 'a> {
+    pool_keys(self.index.keys.get(&self.field,).into_iter(), self.index.pool_id,)
+  }
+  fn contains(&self, raw: &RawKey, _: &T,) -> bool { self.index.keys.get(&self.field,) == Some(raw,) }
+}
+
+impl<T, K,> EqIndex<T, K,> for UniqueIndex<T, K,>
+  where K: Hash + Eq + 'static, {
+  #[allow(private_interfaces,)]
+  fn eq_condition<'a, S,>(self, pool: &'a IndexedPool<T, S,>, field: K,) -> Box<dyn Condition<'a, T,> + 'a>
+    where T: 'static, S: PoolStorage<T,>, {
+    Box::new(UniqueEq { index: pool.index::<Unique<T, K,>>(self.index, self.pool_id,), field, },)
+  }
+}
+
+/// A condition that the field of a [MultiIndex] equals `field`.
+struct MultiEq<'a, T, K,> {
+  index: &'a Multi<T, K,>,
+  pool_id: NonZeroUsize,
+  field: K,
+}
+
+impl<'a, T, K,> Condition<'a, T,> for MultiEq<'a, T, K,>
+  where T: 'static, K: Hash + Eq, {
+  fn describe(&self,) -> String { format!("`{}` multi eq", self.index.name,) }
+  fn estimate(&self, _: usize,) -> usize { self.index.keys.get(&self.field,).map_or(0, |keys,| keys.len(),) }
+  fn keys(self: Box<Self>,) -> Box<dyn Iterator<Item = PoolKey<T,>> + 'a> {
+    pool_keys(self.index.keys.get(&self.field,).into_iter().flatten(), self.pool_id,)
+  }
+  fn contains(&self, raw: &RawKey, _: &T,) -> bool {
+    self.index.keys.get(&self.field,).is_some_and(|keys,| keys.contains(raw,),)
+  }
+}
+
+impl<T, K,> EqIndex<T, K,> for MultiIndex<T, K,>
+  where K: Hash + Eq + 'static, {
+  #[allow(private_interfaces,)]
+  fn eq_condition<'a, S,>(self, pool: &'a IndexedPool<T, S,>, field: K,) -> Box<dyn Condition<'a, T,> + 'a>
+    where T: 'static, S: PoolStorage<T,>, {
+    Box::new(MultiEq { index: pool.index::<Multi<T, K,>>(self.index, self.pool_id,), pool_id: self.pool_id, field, },)
+  }
+}
+
+/// A condition that the field of an [OrderedIndex] is between `start` and `end`.
+struct OrderedRange<'a, T, K,> {
+  index: &'a Ordered<T, K,>,
+  pool_id: NonZeroUsize,
+  start: Bound<K,>,
+  end: Bound<K,>,
+}
+
+impl<T, K,> OrderedRange<'_, T, K,>
+  where K: Ord, {
+  /// Returns the bounds of the condition.
+  #[inline]
+  fn bounds(&self,) -> (Bound<&K,>, Bound<&K,>,) { (self.start.as_ref(), self.end.as_ref(),) }
+  /// Returns `true` if no field meets the condition.
+  #[inline]
+  fn is_empty(&self,) -> bool { !is_valid_range(self.start.as_ref(), self.end.as_ref(),) }
+}
+
+impl<'a, T, K,> Condition<'a, T,> for OrderedRange<'a, T, K,>
+  where T: 'static, K: Ord, {
+  fn describe(&self,) -> String { format!("`{}` range", self.index.name,) }
+  fn estimate(&self, limit: usize,) -> usize {
+    let mut estimate = 0;
+
+    if self.is_empty() { return 0 }
+    for (_, keys,) in self.index.keys.range(self.bounds(),) {
+      estimate += keys.len();
+      if estimate >= limit { break }
+    }
+
+    estimate
+  }
+  #[inline]
+  fn is_range(&self,) -> bool { true }
+  fn keys(self: Box<Self>,) -> Box<dyn Iterator<Item = PoolKey<T,>> + 'a> {
+    if self.is_empty() { return Box::new(std::iter::empty(),) }
+
+    //The bounds are copied out of the condition so the range only borrows the index.
+    let range = self.index.keys.range::<K, _>((self.start, self.end,),);
+
+    pool_keys(range.flat_map(|(_, keys,),| keys,), self.pool_id,)
+  }
+  fn contains(&self, raw: &RawKey, value: &T,) -> bool {
+    let field = (self.index.extract)(value,);
+
+    self.bounds().contains(&field,) && self.index.keys.get(&field,).is_some_and(|keys,| keys.contains(raw,),)
+  }
+}
+
+impl<T, K,> EqIndex<T, K,> for OrderedIndex<T, K,>
+  where K: Ord + Clone + 'static, {
+  #[allow(private_interfaces,)]
+  fn eq_condition<'a, S,>(self, pool: &'a IndexedPool<T, S,>, field: K,) -> Box<dyn Condition<'a, T,> + 'a>
+    where T: 'static, S: PoolStorage<T,>, {
+    let index = pool.index::<Ordered<T, K,>>(self.index, self.pool_id,);
+
+    Box::new(OrderedRange { index, pool_id: self.pool_id, start: Bound::Included(field.clone(),), end: Bound::Included(field,), },)
+  }
+}
+
+/// A filter on the values of a [Query].
+type Filter<'a, T,> = Box<dyn Fn(&T,) -> bool + 'a>;
+
+/// A search for the values of an [IndexedPool] which meet a set of conditions.
+/// 
+/// The indexed condition which the fewest values meet is used to find candidates, which
+/// are intersected with the other indexed conditions by looking each candidate up in
+/// their indexes and then checked against the filters. Without an indexed condition every value is a candidate.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::IndexedPool;
+/// 
+/// struct Order { customer: u32, price: u32, paid: bool, }
+/// 
+/// let mut pool = IndexedPool::new();
+/// let by_customer = pool.add_multi_index("customer", |order: &Order,| order.customer,).unwrap();
+/// let by_price = pool.add_ordered_index("price", |order: &Order,| order.price,).unwrap();
+/// 
+/// for price in 0..100 { pool.insert(Order { customer: price % 10, price, paid: price % 4 == 0, },); }
+/// 
+/// let query = pool.query()
+///   .where_eq(by_customer, 2,)
+///   .where_range(by_price, 10..50,)
+///   .filter(|order,| order.paid,);
+/// 
+/// assert_eq!(query.explain(), "scan `customer` multi eq (10 candidates), intersect `price` range, 1 filter");
+/// 
+/// let mut prices = query.into_iter().map(|(_, order,),| order.price,).collect::<Vec<_>>();
+/// prices.sort_unstable();
+/// assert_eq!(prices, [12, 32]);
+/// ```
+pub struct Query<'a, T, S,> {
+  pool: &'a IndexedPool<T, S,>,
+  conditions: Vec<Box<dyn Condition<'a, T,> + 'a>>,
+  filters: Vec<Filter<'a, T,>>,
+}
+
+impl<'a, T, S,> Query<'a, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  /// Returns a new Query matching every value of `pool`.
+  #[inline]
+  pub(super) fn new(pool: &'a IndexedPool<T, S,>,) -> Self {
+    Self { pool, conditions: Vec::new(), filters: Vec::new(), }
+  }
+  /// Only matches values whose field in `index` equals `field`.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  pub fn where_eq<K, I,>(mut self, index: I, field: K,) -> Self
+    where I: EqIndex<T, K,>, {
+    self.conditions.push(index.eq_condition(self.pool, field,),);
+    self
+  }
+  /// Only matches values whose field in `index` is in `range`.
+  /// 
+  /// An empty or inverted `range` matches no values.
+  /// 
+  /// # Panics
+  /// 
+  /// If `index` was registered with another IndexedPool.
+  pub fn where_range<K, R,>(mut self, index: OrderedIndex<T, K,>, range: R,) -> Self
+    where K: Ord + Clone + 'static, R: RangeBounds<K>, {
+    self.conditions.push(Box::new(OrderedRange {
+      index: self.pool.index::<Ordered<T, K,>>(index.index, index.pool_id,),
+      pool_id: index.pool_id,
+      start: range.start_bound().cloned(),
+      end: range.end_bound().cloned(),
+    },),);
+    self
+  }
+  /// Only matches values for which `filter` returns `true`.
+  pub fn filter<F,>(mut self, filter: F,) -> Self
+    where F: Fn(&T,) -> bool + 'a, {
+    self.filters.push(Box::new(filter,),);
+    self
+  }
+  /// Returns the position of the condition the fewest values meet and that number.
+  fn driver(&self,) -> Option<(usize, usize,)> {
+    //Ranges are estimated last so they stop counting at the best estimate so far.
+    let (ranges, others,): (Vec<_>, Vec<_>,) = (0..self.conditions.len()).partition(|&position,| self.conditions[position].is_range(),);
+    let mut driver = None;
+
+    for position in others.into_iter().chain(ranges,) {
+      let limit = driver.map_or(usize::MAX, |(_, estimate,),| estimate,);
+      let estimate = self.conditions[position].estimate(limit,);
+
+      if estimate < limit { driver = Some((position, estimate,),) }
+    }
+
+    driver
+  }
+  /// Returns a description of how the Query will find its values.
+  pub fn explain(&self,) -> String {
+    let mut plan = match self.driver() {
+      Some((driver, estimate,),) => {
+        let checks = self.conditions.iter().enumerate()
+          .filter(|&(position, _,),| position != driver,)
+          .map(|(_, condition,),| format!(", intersect {}", condition.describe(),),)
+          .collect::<String>();
+
+        format!("scan {} ({}){}", self.conditions[driver].describe(), candidates(estimate,), checks,)
+      },
+      None => format!("scan pool ({})", candidates(self.pool.len(),),),
+    };
+
+    match self.filters.len() {
+      0 => {},
+      1 => plan.push_str(", 1 filter",),
+      filters => plan.push_str(&format!(", {} filters", filters,),),
+    }
+
+    plan
+  }
+}
+
+/// Returns the number of candidates for [Query::explain].
+fn candidates(count: usize,) -> String {
+  match count {
+    1 => "1 candidate".to_string(),
+    count => format!("{} candidates", count,),
+  }
+}
+
+impl<'a, T, S,> IntoIterator for Query<'a, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, &'a T,);
+  type IntoIter = QueryIter<'a, T,>;
+
+  fn into_iter(mut self,) -> Self::IntoIter {
+    let pool = &self.pool.pool;
+    let candidates = match self.driver() {
+      Some((driver, _,),) => self.conditions.remove(driver,).keys(),
+      None => Box::new(pool.keys(),),
+    };
+    let (conditions, filters,) = (self.conditions, self.filters,);
+
+    QueryIter(Box::new(candidates.map(move |key,| (key, &pool[key],),)
+      .filter(move |&(key, value,),| {
+        conditions.iter().all(|condition,| condition.contains(&raw(key,), value,),)
+        && filters.iter().all(|filter,| filter(value,),)
+      },),),)
+  }
+}
+
+impl<T, S,> fmt::Debug for Query<'_, T, S,>
+  where T: 'static, S: PoolStorage<T,>, {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_tuple("Query",).field(&self.explain(),).finish()
+  }
+}
+
+/// An iterator over the [PoolKey]s and values matched by a [Query].
+pub struct QueryIter<'a, T,>(Box<dyn Iterator<Item = (PoolKey<T,>, &'a T,)> + 'a>,);
+
+impl<'a, T,> Iterator for QueryIter<'a, T,> {
+  type Item = (PoolKey<T,>, &'a T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next() }
+}
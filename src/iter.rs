@@ -0,0 +1,140 @@
+//! Defines the iterators over a [TypePool].
+
+use crate::{TypePool, PoolKey, storage::PoolStorage,};
+use std::{num::NonZeroUsize, marker::PhantomData,};
+
+/// An iterator over the [PoolKey]s and values of a [TypePool].
+pub struct Iter<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  pub(crate) iter: S::Iter<'a,>,
+  pub(crate) pool_id: NonZeroUsize,
+}
+
+impl<'a, T, S,> Iterator for Iter<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  type Item = (PoolKey<T,>, &'a T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.iter.next()
+    .map(|(index, generation, value,),| (PoolKey(index, self.pool_id, generation, PhantomData,), value,),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.iter.size_hint() }
+}
+
+/// An iterator over the [PoolKey]s and mutable values of a [TypePool].
+pub struct IterMut<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  pub(crate) iter: S::IterMut<'a,>,
+  pub(crate) pool_id: NonZeroUsize,
+}
+
+impl<'a, T, S,> Iterator for IterMut<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  type Item = (PoolKey<T,>, &'a mut T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.iter.next()
+    .map(|(index, generation, value,),| (PoolKey(index, self.pool_id, generation, PhantomData,), value,),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.iter.size_hint() }
+}
+
+/// An iterator which moves the [PoolKey]s and values out of a [TypePool].
+pub struct IntoIter<T, S,>
+  where S: PoolStorage<T,>, {
+  pub(crate) iter: S::IntoEntries,
+  pub(crate) pool_id: NonZeroUsize,
+}
+
+impl<T, S,> Iterator for IntoIter<T, S,>
+  where S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.iter.next()
+    .map(|(index, generation, value,),| (PoolKey(index, self.pool_id, generation, PhantomData,), value,),)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.iter.size_hint() }
+}
+
+/// An iterator which removes every value from a [TypePool].
+/// 
+/// The TypePool is emptied as soon as the iterator is created.
+pub type Drain<T, S,> = IntoIter<T, S,>;
+
+/// An iterator over the [PoolKey]s of a [TypePool].
+pub struct Keys<'a, T, S,>(pub(crate) Iter<'a, T, S,>,)
+  where S: PoolStorage<T,> + 'a, T: 'a;
+
+impl<'a, T, S,> Iterator for Keys<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  type Item = PoolKey<T,>;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next().map(|(key, _,),| key,) }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.0.size_hint() }
+}
+
+/// An iterator over the values of a [TypePool].
+pub struct Values<'a, T, S,>(pub(crate) S::Iter<'a,>,)
+  where S: PoolStorage<T,> + 'a, T: 'a;
+
+impl<'a, T, S,> Iterator for Values<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  type Item = &'a T;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next().map(|(_, _, value,),| value,) }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.0.size_hint() }
+}
+
+/// An iterator over the mutable values of a [TypePool].
+pub struct ValuesMut<'a, T, S,>(pub(crate) S::IterMut<'a,>,)
+  where S: PoolStorage<T,> + 'a, T: 'a;
+
+impl<'a, T, S,> Iterator for ValuesMut<'a, T, S,>
+  where S: PoolStorage<T,> + 'a, T: 'a, {
+  type Item = &'a mut T;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next().map(|(_, _, value,),| value,) }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { self.0.size_hint() }
+}
+
+impl<T, S,> IntoIterator for TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, T,);
+  type IntoIter = IntoIter<T, S,>;
+
+  #[inline]
+  fn into_iter(self,) -> Self::IntoIter {
+    IntoIter { iter: self.pool.into_entries(), pool_id: self.pool_id, }
+  }
+}
+
+impl<'a, T, S,> IntoIterator for &'a TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, &'a T,);
+  type IntoIter = Iter<'a, T, S,>;
+
+  #[inline]
+  fn into_iter(self,) -> Self::IntoIter { self.iter() }
+}
+
+impl<'a, T, S,> IntoIterator for &'a mut TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  type Item = (PoolKey<T,>, &'a mut T,);
+  type IntoIter = IterMut<'a, T, S,>;
+
+  #[inline]
+  fn into_iter(self,) -> Self::IntoIter { self.iter_mut() }
+}
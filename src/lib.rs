@@ -15,8 +15,10 @@
 //! assert_eq!(pool[key2], 1);
 //! assert_eq!(pool[key3], 10);
 //! 
-//! let keys = [key1, key2, key3].iter().cloned().collect();
-//! let values = pool.get_set(&keys);
+//! let [value1, value3] = pool.get_disjoint_mut([key1, key3],).unwrap();
+//! *value1 += *value3;
+//! 
+//! assert_eq!(pool[key1], 20);
 //! ```
 //! 
 //! Author --- daniel.bechaz@gmail.com  
@@ -25,22 +27,69 @@
 #![deny(missing_docs,)]
 
 use std::{
-  hash, ops,
+  hash, ops, fmt,
+  convert::TryInto,
   num::NonZeroUsize,
-  collections::{HashMap, HashSet,},
+  collections::HashSet,
   marker::PhantomData,
+  sync::atomic::{AtomicUsize, Ordering,},
 };
 
-#[macro_use]
-extern crate subvert;
+mod error;
+mod entry;
+pub mod storage;
+pub mod iter;
+pub mod sync;
+mod append;
+mod rc;
+mod secondary;
+mod track;
+mod transaction;
+mod history;
+mod snapshot;
+mod raw;
+mod any;
+pub mod indexed;
+#[cfg(feature = "serde",)]
+mod serialize;
+#[cfg(feature = "rayon",)]
+pub mod par;
+
+pub use self::{
+  error::PoolError,
+  entry::VacantEntry,
+  sync::SyncTypePool,
+  append::AppendPool,
+  rc::{RcPool, StrongKey, WeakKey,},
+  secondary::{SecondaryMap, SparseSecondaryMap,},
+  indexed::{IndexedPool, IndexError,},
+  track::{Changes, ObserverId,},
+  transaction::Transaction,
+  history::HistoryPool,
+  snapshot::{PoolSnapshot, SnapshotIter, SnapshotKeys, SnapshotValues,},
+  raw::RawPoolKey,
+  any::{AnyPool, TypeIter, TypeIterMut,},
+};
+#[cfg(feature = "serde",)]
+pub use self::serialize::KeyRemap;
+use self::{storage::{PoolStorage, DefaultStorage,}, track::Tracker,};
 
 /// A key issued by a [TypePool].
-pub struct PoolKey<T,>(usize, NonZeroUsize, PhantomData<T,>,);
+/// 
+/// Each key carries the generation of the value it was issued for, so a key to a
+/// removed value never refers to a value inserted after it.
+pub struct PoolKey<T,>(usize, NonZeroUsize, usize, PhantomData<T,>,);
+
+impl<T,> PoolKey<T,> {
+  /// Returns this key as a key to `U` values.
+  #[inline]
+  pub(crate) fn cast<U,>(self,) -> PoolKey<U,> { PoolKey(self.0, self.1, self.2, PhantomData,) }
+}
 
 impl<T,> PartialEq for PoolKey<T,> {
   #[inline]
   fn eq(&self, rhs: &Self,) -> bool {
-    self.0 == rhs.0 && self.1 == rhs.1
+    self.0 == rhs.0 && self.1 == rhs.1 && self.2 == rhs.2
   }
 }
 
@@ -52,37 +101,199 @@ impl<T,> Clone for PoolKey<T,> {
 
 impl<T,> Copy for PoolKey<T,> {}
 
+impl<T,> fmt::Debug for PoolKey<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_struct("PoolKey",)
+    .field("id", &self.0,)
+    .field("generation", &self.2,)
+    .field("pool", &self.1,)
+    .finish()
+  }
+}
+
 impl<T,> hash::Hash for PoolKey<T,> {
   #[inline]
   fn hash<H: hash::Hasher,>(&self, hasher: &mut H,) {
-    self.0.hash(hasher,)
+    self.0.hash(hasher,);
+    self.2.hash(hasher,)
   }
 }
 
+/// The state of a [PoolKey] relative to a [TypePool].
+#[derive(PartialEq, Eq, Clone, Copy, Debug,)]
+pub enum KeyState {
+  /// The key refers to a value in the TypePool.
+  Live,
+  /// The key was issued by the TypePool but its value has since been removed.
+  Removed,
+  /// The key was never issued by the TypePool.
+  Unknown,
+}
+
+/// The source of unique [TypePool] identities.
+/// 
+/// Starts at `1` so that every identity fits in a `NonZeroUsize`.
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(1,);
+
+/// Returns a new process wide unique pool identity.
+/// 
+/// # Panics
+/// 
+/// If all identities have been issued.
+fn next_pool_id() -> NonZeroUsize {
+  let id = NEXT_POOL_ID.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id,| id.checked_add(1,),)
+    .expect("`TypePool` identities exhausted",);
+
+  unsafe { NonZeroUsize::new_unchecked(id,) }
+}
+
 /// A pool of `T` values.
-pub struct TypePool<T,> {
-  pool: Box<HashMap<usize, T,>>,
-  next_id: usize,
+/// 
+/// Each TypePool has a unique identity which is independent of its location in memory,
+/// so a TypePool can be moved freely without invalidating the [PoolKey]s it issued.
+/// 
+/// Every inserted value is also given a unique generation, so a [PoolKey] to a removed
+/// value is reported as removed even after its id has been reused.
+/// 
+/// The values are kept in a [PoolStorage] `S`. By default values are stored contiguously
+/// and ids are allocated from a free list, so inserting, removing and indexing are all
+/// `O(1)`; see the [storage] module for the alternatives.
+/// 
+/// A TypePool can record which values change and call observers as values are inserted
+/// and removed; see [TypePool::track_changes].
+pub struct TypePool<T, S = DefaultStorage<T,>,> {
+  pool: S,
+  next_generation: usize,
+  pool_id: NonZeroUsize,
+  tracker: Tracker<T,>,
+  _values: PhantomData<T,>,
 }
 
 impl<T,> TypePool<T,> {
   /// Returns a new empty TypePool.
   #[inline]
-  pub fn new() -> Self {
+  pub fn new() -> Self { Self::default() }
+  /// Returns a new empty TypePool with room for at least `capacity` values.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let pool = TypePool::<i32>::with_capacity(10,);
+  /// 
+  /// assert!(pool.capacity() >= 10);
+  /// ```
+  #[inline]
+  pub fn with_capacity(capacity: usize,) -> Self { Self::with_capacity_in(capacity,) }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Returns a new TypePool which keeps its values in `pool`.
+  /// 
+  /// # Params
+  /// 
+  /// pool --- The empty storage to use.  
+  fn with_storage(pool: S,) -> Self {
+    debug_assert!(pool.is_empty(), "`TypePool::with_storage` `pool` is not empty",);
+
     Self {
-      pool: Box::new(HashMap::new()),
-      next_id: 0,
+      pool,
+      next_generation: 0,
+      pool_id: next_pool_id(),
+      tracker: Tracker::new(),
+      _values: PhantomData,
     }
   }
+  /// Returns a new empty TypePool whose storage has room for at least `capacity` values.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, storage::HashStorage,};
+  /// 
+  /// let pool = TypePool::<i32, HashStorage<_>>::with_capacity_in(10,);
+  /// 
+  /// assert!(pool.capacity() >= 10);
+  /// ```
+  #[inline]
+  pub fn with_capacity_in(capacity: usize,) -> Self { Self::with_storage(S::with_capacity(capacity,),) }
   /// Returns `true` if `key` was issued by this TypePool.
   #[inline]
   pub fn owns_key(&self, key: &PoolKey<T,>,) -> bool {
-    key.1.get() == self as *const Self as usize
+    key.1 == self.pool_id
   }
   /// Returns `true` if this TypePool contains `key`.
   #[inline]
   pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool {
-    self.owns_key(key,) && self.pool.contains_key(&key.0,)
+    self.key_state(key,) == KeyState::Live
+  }
+  /// Returns the [KeyState] of `key` in this TypePool.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, KeyState,};
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key = pool.insert(10,);
+  /// 
+  /// assert_eq!(pool.key_state(&key,), KeyState::Live);
+  /// pool.remove(key,);
+  /// assert_eq!(pool.key_state(&key,), KeyState::Removed);
+  /// assert_eq!(TypePool::new().key_state(&key,), KeyState::Unknown);
+  /// ```
+  pub fn key_state(&self, key: &PoolKey<T,>,) -> KeyState {
+    if !self.owns_key(key,) || key.2 >= self.next_generation { return KeyState::Unknown }
+
+    match self.pool.get(key.0,) {
+      Some((generation, _,)) if generation == key.2 => KeyState::Live,
+      _ => KeyState::Removed,
+    }
+  }
+  /// Returns `Ok` if `key` refers to a value in this TypePool.
+  fn check_key(&self, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+    match self.key_state(key,) {
+      KeyState::Live => Ok(()),
+      KeyState::Removed => Err(PoolError::StaleKey),
+      KeyState::Unknown if self.owns_key(key,) => Err(PoolError::MissingKey),
+      KeyState::Unknown => Err(PoolError::ForeignKey),
+    }
+  }
+  /// Returns a reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.  
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, PoolError,};
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key = pool.insert(10,);
+  /// 
+  /// assert_eq!(pool.get(key,), Ok(&10));
+  /// pool.remove(key,);
+  /// assert_eq!(pool.get(key,), Err(PoolError::StaleKey));
+  /// ```
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&T, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(self.pool.get(key.0,).unwrap().1)
+  }
+  /// Returns a mutable reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.  
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut T, PoolError> {
+    self.check_key(&key,)?;
+    self.tracker.modified(key,);
+
+    Ok(self.pool.get_mut(key.0,).unwrap().1)
   }
   /// Returns the number of values in this TypePool.
   #[inline]
@@ -90,34 +301,53 @@ impl<T,> TypePool<T,> {
   /// Returns `true` the TypePool is empty.
   #[inline]
   pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Returns the number of values this TypePool can hold without reallocating.
+  #[inline]
+  pub fn capacity(&self,) -> usize { self.pool.capacity() }
+  /// Reserves room for at least `additional` more values.
+  #[inline]
+  pub fn reserve(&mut self, additional: usize,) { self.pool.reserve(additional,) }
+  /// Reserves room for exactly `additional` more values where the storage allows it.
+  #[inline]
+  pub fn reserve_exact(&mut self, additional: usize,) { self.pool.reserve_exact(additional,) }
+  /// Releases as much unused memory as the storage allows.
+  /// 
+  /// The [PoolKey]s of the values in this TypePool remain valid.
+  #[inline]
+  pub fn shrink_to_fit(&mut self,) { self.pool.shrink_to_fit() }
   /// Inserts `value` into the TypePool.
   /// 
   /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the TypePool is full.
   pub fn insert(&mut self, value: T,) -> PoolKey<T,> {
-    use std::usize;
-
-    impl<T,> TypePool<T,> {
-      fn get_next_id(&mut self,) -> usize {
-        let id = (self.next_id..=usize::MAX)
-          .chain(0..self.next_id,)
-          .find(|key,| !self.pool.contains_key(key,),)
-          .unwrap();
-        
-        self.next_id = id + 1;
-        id
-      }
-    }
-
-    assert_ne!(self.len(), usize::MAX, "`TypePool` is full",);
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("{}", e),)
+  }
+  /// Attempts to insert `value` into the TypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the TypePool is full.
+  pub fn try_insert(&mut self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)> {
+    let generation = self.next_generation;
+    let next = match generation.checked_add(1,) {
+      Some(next,) => next,
+      None => return Err((PoolError::CapacityExhausted, value,)),
+    };
+    let id = self.pool.insert(generation, value,)
+      .map_err(|value,| (PoolError::CapacityExhausted, value,),)?;
 
-    let id = self.get_next_id();
+    let key = PoolKey(id, self.pool_id, generation, PhantomData,);
 
-    self.pool.insert(id, value,);
+    self.next_generation = next;
+    if self.tracker.is_active() { self.tracker.inserted(key, self.pool.get(id,).unwrap().1,) }
 
-    PoolKey(id, unsafe { NonZeroUsize::new_unchecked(&mut self.pool as *const _ as usize,) }, PhantomData,)
+    Ok(key)
   }
   /// Removes the value mapped too [PoolKey].
   /// 
+  /// Returns `None` if the value has already been removed.
+  /// 
   /// # Params
   /// 
   /// key --- The key of the value to remove.  
@@ -128,37 +358,199 @@ impl<T,> TypePool<T,> {
   pub fn remove(&mut self, key: PoolKey<T,>,) -> Option<T> {
     assert!(self.owns_key(&key,), "`PoolKey::delete` `key` must be owned by this pool",);
 
-    self.pool.remove(&key.0,)
+    self.try_remove(key,).ok()
   }
-  /// Returns unique references too all the values referenced by `keys`.
-  /// 
-  /// The index of a [PoolKey] in the output is the index of the corresponding value.
+  /// Attempts to remove the value mapped too [PoolKey].
   /// 
   /// # Params
   /// 
-  /// keys --- The set of [PoolKey]s to get references too.  
+  /// key --- The key of the value to remove.  
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<T, PoolError> {
+    self.check_key(&key,)?;
+
+    let value = self.pool.remove(key.0,).unwrap().1;
+
+    self.tracker.removed(key, &value,);
+    Ok(value)
+  }
+  /// Inserts `value` back under the removed `key`.
   /// 
   /// # Panics
   /// 
-  /// If any of the keys in `keys` are not in this TypePool.
+  /// If the index of `key` is occupied or the storage cannot fill it.
+  fn restore(&mut self, key: PoolKey<T,>, value: T,) {
+    debug_assert!(self.key_state(&key,) == KeyState::Removed, "`TypePool::restore` `key` was not removed",);
+
+    if self.pool.insert_at(key.0, key.2, value,).is_err() {
+      if self.pool.get(key.0,).is_some() { panic!("`TypePool::restore` index is occupied") }
+      else { panic!("`TypePool::restore` storage cannot fill the index") }
+    }
+    if self.tracker.is_active() { self.tracker.restored(key, self.pool.get(key.0,).unwrap().1,) }
+  }
+  /// Returns unique references too the values mapped too each of `keys` in the same order.
+  fn get_disjoint_vec_mut(&mut self, keys: &[PoolKey<T,>],) -> Result<Vec<&mut T>, PoolError> {
+    let indices = keys.iter()
+      .map(|key,| self.check_key(key,).map(|_,| key.0,),)
+      .collect::<Result<Vec<_>, _>>()?;
+
+    //Every key is live so the only failure is a repeated key.
+    let values = self.pool.get_disjoint_mut(&indices,).ok_or(PoolError::DuplicateKey,)?;
+
+    for &key in keys { self.tracker.modified(key,); }
+    Ok(values)
+  }
+  /// Returns unique references too the values mapped too each of `keys`.
+  /// 
+  /// The reference at each index of the output belongs to the key at the same index of
+  /// `keys`.
+  /// 
+  /// # Params
+  /// 
+  /// keys --- The distinct [PoolKey]s to get references too.  
   /// 
   /// # Example
   /// 
   /// ```
-  /// use type_pool::TypePool;
+  /// use type_pool::{TypePool, PoolError,};
   /// 
   /// let mut pool = TypePool::new();
   /// let key1 = pool.insert(10,);
   /// let key2 = pool.insert(1,);
   /// 
-  /// let keys = [key1, key2].iter().cloned().collect();
-  /// let values = pool.get_set(&keys);
+  /// let [value2, value1] = pool.get_disjoint_mut([key2, key1],).unwrap();
+  /// std::mem::swap(value1, value2,);
+  /// 
+  /// assert_eq!(pool[key1], 1);
+  /// assert_eq!(pool.get_disjoint_mut([key1, key1],).err(), Some(PoolError::DuplicateKey));
   /// ```
+  pub fn get_disjoint_mut<const N: usize,>(&mut self, keys: [PoolKey<T,>; N],) -> Result<[&mut T; N], PoolError> {
+    let values = self.get_disjoint_vec_mut(&keys,)?;
+
+    Ok(values.try_into().unwrap_or_else(|_,| unreachable!(),))
+  }
+  /// Returns unique references too the values mapped too each of `keys`.
+  /// 
+  /// The reference at each index of the output belongs to the key at the same index of
+  /// `keys`.
+  /// 
+  /// # Params
+  /// 
+  /// keys --- The distinct [PoolKey]s to get references too.  
+  pub fn get_disjoint_slice_mut(&mut self, keys: &[PoolKey<T,>],) -> Result<Box<[&mut T]>, PoolError> {
+    self.get_disjoint_vec_mut(keys,).map(Vec::into_boxed_slice,)
+  }
+  /// Returns unique references too all the values referenced by `keys`.
+  /// 
+  /// The order of the output is unspecified, use [TypePool::get_disjoint_mut] or
+  /// [TypePool::get_disjoint_slice_mut] to know which value belongs to which key.
+  /// 
+  /// # Params
+  /// 
+  /// keys --- The set of [PoolKey]s to get references too.  
+  /// 
+  /// # Panics
+  /// 
+  /// If any of the keys in `keys` are not in this TypePool or have been removed.
+  #[deprecated(note = "use `get_disjoint_mut` or `get_disjoint_slice_mut` instead",)]
+  #[allow(deprecated,)]
   pub fn get_set(&mut self, keys: &HashSet<PoolKey<T,>>,) -> Box<[&mut T]> {
-    keys.iter()
-    .cloned()
-    .map(|key,| unsafe { steal!(&mut self[key]) },)
-    .collect()
+    self.try_get_set(keys,).unwrap_or_else(|e,| panic!("`TypePool::get_set` {}", e),)
+  }
+  /// Attempts to get unique references too all the values referenced by `keys`.
+  /// 
+  /// The order of the output is unspecified, use [TypePool::get_disjoint_mut] or
+  /// [TypePool::get_disjoint_slice_mut] to know which value belongs to which key.
+  /// 
+  /// # Params
+  /// 
+  /// keys --- The set of [PoolKey]s to get references too.  
+  #[deprecated(note = "use `get_disjoint_mut` or `get_disjoint_slice_mut` instead",)]
+  pub fn try_get_set(&mut self, keys: &HashSet<PoolKey<T,>>,) -> Result<Box<[&mut T]>, PoolError> {
+    self.get_disjoint_slice_mut(&keys.iter().cloned().collect::<Vec<_>>(),)
+  }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Returns an iterator over the [PoolKey]s and values of this TypePool.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let (pool, keys,) = TypePool::from_iter(vec![1, 2, 3],);
+  /// 
+  /// for (key, value,) in pool.iter() {
+  ///   assert!(pool.owns_key(&key,));
+  ///   assert_eq!(pool[key], *value);
+  /// }
+  /// assert_eq!(pool.iter().count(), keys.len());
+  /// ```
+  #[inline]
+  pub fn iter(&self,) -> iter::Iter<'_, T, S,> {
+    iter::Iter { iter: self.pool.iter(), pool_id: self.pool_id, }
+  }
+  /// Returns an iterator over the [PoolKey]s and mutable values of this TypePool.
+  #[inline]
+  pub fn iter_mut(&mut self,) -> iter::IterMut<'_, T, S,> {
+    self.modified_all();
+    iter::IterMut { iter: self.pool.iter_mut(), pool_id: self.pool_id, }
+  }
+  /// Returns an iterator over the [PoolKey]s of this TypePool.
+  #[inline]
+  pub fn keys(&self,) -> iter::Keys<'_, T, S,> { iter::Keys(self.iter(),) }
+  /// Returns an iterator over the values of this TypePool.
+  #[inline]
+  pub fn values(&self,) -> iter::Values<'_, T, S,> { iter::Values(self.pool.iter(),) }
+  /// Returns an iterator over the mutable values of this TypePool.
+  #[inline]
+  pub fn values_mut(&mut self,) -> iter::ValuesMut<'_, T, S,> {
+    self.modified_all();
+    iter::ValuesMut(self.pool.iter_mut(),)
+  }
+  /// Removes every value from this TypePool and returns an iterator over them.
+  /// 
+  /// The [PoolKey]s of the removed values are never reissued.
+  pub fn drain(&mut self,) -> iter::Drain<T, S,> {
+    if self.tracker.is_active() {
+      for (index, generation, value,) in self.pool.iter() {
+        self.tracker.removed(PoolKey(index, self.pool_id, generation, PhantomData,), value,);
+      }
+    }
+
+    iter::IntoIter { iter: std::mem::take(&mut self.pool,).into_entries(), pool_id: self.pool_id, }
+  }
+  /// Removes every value for which `keep` returns `false`.
+  /// 
+  /// `keep` may change the values so every kept value is recorded as modified.
+  /// 
+  /// # Params
+  /// 
+  /// keep --- Called with the [PoolKey] and value of every value.  
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let (mut pool, keys,) = TypePool::from_iter(0..10,);
+  /// 
+  /// pool.retain(|_, value,| *value % 2 == 0,);
+  /// assert_eq!(pool.len(), 5);
+  /// assert!(!pool.contains_key(&keys[1],));
+  /// ```
+  pub fn retain<F,>(&mut self, mut keep: F,)
+    where F: FnMut(PoolKey<T,>, &mut T,) -> bool, {
+    let (pool_id, tracker,) = (self.pool_id, &mut self.tracker,);
+
+    self.pool.retain(|index, generation, value,| {
+      let key = PoolKey(index, pool_id, generation, PhantomData,);
+      let kept = keep(key, value,);
+
+      if kept { tracker.modified(key,) } else { tracker.removed(key, value,) }
+      kept
+    },)
   }
 }
 
@@ -172,12 +564,13 @@ impl<T,> TypePool<T,> {
   pub fn from_iter<I,>(iter: I,) -> (Self, Box<[PoolKey<T,>]>,)
     where I: IntoIterator<Item = T>, {
     let iter = iter.into_iter();
-    let mut pool = TypePool::new();
-    let mut keys = {
+    let cap = {
       let cap = iter.size_hint();
 
-      Vec::with_capacity(cap.1.unwrap_or(cap.0,),)
+      cap.1.unwrap_or(cap.0,)
     };
+    let mut pool = TypePool::with_capacity(cap,);
+    let mut keys = Vec::with_capacity(cap,);
 
     keys.extend(iter.map(|v,| pool.insert(v,),),);
 
@@ -185,28 +578,27 @@ impl<T,> TypePool<T,> {
   }
 }
 
-impl<T,> Default for TypePool<T,> {
+impl<T, S,> Default for TypePool<T, S,>
+  where S: PoolStorage<T,>, {
   #[inline]
-  fn default() -> Self { Self::new() }
+  fn default() -> Self { Self::with_storage(S::default(),) }
 }
 
-impl<T,> ops::Index<PoolKey<T,>> for TypePool<T,> {
+impl<T, S,> ops::Index<PoolKey<T,>> for TypePool<T, S,>
+  where S: PoolStorage<T,>, {
   type Output = T;
 
   #[inline]
   fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
-    assert!(self.owns_key(&key,), "`TypePool::index` `key` must be issued from the pool",);
-
-    self.pool.get(&key.0,).expect("`TypePool::index` `key` does not exist",)
+    self.get(key,).unwrap_or_else(|e,| panic!("`TypePool::index` {}", e),)
   }
 }
 
-impl<T,> ops::IndexMut<PoolKey<T,>> for TypePool<T,> {
+impl<T, S,> ops::IndexMut<PoolKey<T,>> for TypePool<T, S,>
+  where S: PoolStorage<T,>, {
   #[inline]
   fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
-    assert!(self.owns_key(&key,), "`TypePool::index_mut` `key` must be issued from the pool",);
-
-    self.pool.get_mut(&key.0,).expect("`TypePool::index_mut` `key` does not exist",)
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`TypePool::index_mut` {}", e),)
   }
 }
 
@@ -215,6 +607,18 @@ mod tests {
   use super::*;
 
   #[test]
+  fn test_with_capacity() {
+    let mut pool = TypePool::<i32, storage::HashStorage<_,>,>::with_capacity_in(16,);
+    let capacity = pool.capacity();
+
+    assert!(capacity >= 16, "`TypePool::with_capacity_in` did not reserve room",);
+    for value in 0..16 { pool.insert(value,); }
+    assert_eq!(pool.capacity(), capacity, "`TypePool::with_capacity_in` reserved too little room",);
+    assert!(TypePool::<i32,>::with_capacity(16,).capacity() >= 16, "`TypePool::with_capacity` did not reserve room",);
+  }
+
+  #[test]
+  #[allow(deprecated,)]
   fn test_type_pool() {
     let mut pool = TypePool::new();
     let key1 = pool.insert(4,);
@@ -236,4 +640,117 @@ mod tests {
     let value = pool.remove(key1,).expect("`TypePool::remove` returned no value");
     assert_eq!(value, 1, "`TypePool::remove` returned wrong value",);
   }
+  #[test]
+  fn test_pool_identity() {
+    let (pool, keys,) = TypePool::from_iter(vec![1, 2, 3],);
+    let mut pools = vec![pool];
+    let other = TypePool::<i32,>::new();
+
+    assert!(keys.iter().all(|key,| pools[0].contains_key(key,),), "`TypePool` rejected its keys after a move",);
+    assert!(keys.iter().all(|key,| !other.owns_key(key,),), "`TypePool` accepted foreign keys",);
+
+    let pool = Box::new(pools.pop().unwrap(),);
+    assert_eq!(pool[keys[1]], 2, "`TypePool::index` failed after a move",);
+  }
+  #[test]
+  fn test_generations() {
+    let mut pool = TypePool::new();
+    let key1 = pool.insert(1,);
+
+    pool.remove(key1,);
+    let key2 = pool.insert(2,);
+    assert_eq!(key1.0, key2.0, "`TypePool::insert` did not reuse the id",);
+    assert_eq!(pool.key_state(&key1,), KeyState::Removed, "`TypePool::key_state` stale key is live",);
+    assert!(!pool.contains_key(&key1,), "`TypePool::contains_key` accepted a stale key",);
+    assert!(pool.remove(key1,).is_none(), "`TypePool::remove` removed through a stale key",);
+    assert_eq!(pool[key2], 2, "`TypePool::index` returned wrong value",);
+
+    let forged = PoolKey(key2.0, key2.1, key2.2 + 1, PhantomData,);
+    assert_eq!(pool.key_state(&forged,), KeyState::Unknown, "`TypePool::key_state` accepted an unissued key",);
+  }
+  #[test]
+  #[should_panic(expected = "`TypePool::index` `key` has been removed",)]
+  fn test_stale_index() {
+    let mut pool = TypePool::new();
+    let key = pool.insert(1,);
+
+    pool.remove(key,);
+    pool.insert(2,);
+    let _ = pool[key];
+  }
+  #[test]
+  #[allow(deprecated,)]
+  fn test_errors() {
+    let mut pool = TypePool::new();
+    let mut other = TypePool::new();
+    let key = pool.insert(1,);
+    let foreign = other.insert(2,);
+    let missing = PoolKey(key.0, key.1, key.2 + 1, PhantomData,);
+
+    assert_eq!(pool.get(foreign,), Err(PoolError::ForeignKey), "`TypePool::get` accepted a foreign key",);
+    assert_eq!(pool.get(missing,), Err(PoolError::MissingKey), "`TypePool::get` accepted a missing key",);
+    assert_eq!(pool.get_mut(key,).map(|v,| *v,), Ok(1), "`TypePool::get_mut` failed",);
+    assert_eq!(pool.try_remove(key,), Ok(1), "`TypePool::try_remove` failed",);
+    assert_eq!(pool.try_remove(key,), Err(PoolError::StaleKey), "`TypePool::try_remove` removed a stale key",);
+    assert_eq!(
+      pool.try_get_set(&[foreign,].iter().cloned().collect(),).err(), Some(PoolError::ForeignKey),
+      "`TypePool::try_get_set` accepted a foreign key",
+    );
+
+    assert_eq!(
+      pool.get_disjoint_slice_mut(&[foreign,],).err(), Some(PoolError::ForeignKey),
+      "`TypePool::get_disjoint_slice_mut` accepted a foreign key",
+    );
+
+    pool.next_generation = usize::MAX;
+    assert_eq!(pool.try_insert(3,).err(), Some((PoolError::CapacityExhausted, 3,)), "`TypePool::try_insert` exceeded capacity",);
+  }
+  #[test]
+  fn test_disjoint_mut() {
+    let (mut pool, keys,) = TypePool::from_iter(0..4,);
+    let [a, b, c,] = pool.get_disjoint_mut([keys[2], keys[0], keys[3],],)
+      .expect("`TypePool::get_disjoint_mut` failed",);
+
+    //Write through every reference while they are all alive.
+    *a += 10; *b += 20; *c += 30;
+    assert_eq!((*a, *b, *c,), (12, 20, 33,), "`TypePool::get_disjoint_mut` returned wrong order",);
+
+    let values = pool.get_disjoint_slice_mut(&[keys[1], keys[2],],)
+      .expect("`TypePool::get_disjoint_slice_mut` failed",);
+    assert_eq!(values.iter().map(|v,| **v,).collect::<Vec<_>>(), [1, 12], "`TypePool::get_disjoint_slice_mut` returned wrong order",);
+
+    assert_eq!(
+      pool.get_disjoint_slice_mut(&[keys[1], keys[2], keys[1],],).err(), Some(PoolError::DuplicateKey),
+      "`TypePool::get_disjoint_slice_mut` accepted duplicate keys",
+    );
+    pool.remove(keys[0],);
+    assert_eq!(
+      pool.get_disjoint_mut([keys[1], keys[0],],).err(), Some(PoolError::StaleKey),
+      "`TypePool::get_disjoint_mut` accepted a stale key",
+    );
+  }
+  #[test]
+  fn test_iter() {
+    let (mut pool, keys,) = TypePool::from_iter(0..6,);
+
+    pool.remove(keys[2],);
+    assert_eq!(pool.keys().collect::<Vec<_>>(), [keys[0], keys[1], keys[3], keys[4], keys[5]], "`TypePool::keys` returned wrong keys",);
+    assert!(pool.iter().all(|(key, value,),| pool.contains_key(&key,) && pool[key] == *value,), "`TypePool::iter` returned wrong entries",);
+
+    for value in pool.values_mut() { *value *= 10; }
+    for (_, value,) in &mut pool { *value += 1; }
+    assert_eq!(pool.values().cloned().collect::<Vec<_>>(), [1, 11, 31, 41, 51], "`TypePool::values` returned wrong values",);
+
+    pool.retain(|key, _,| key != keys[3],);
+    assert_eq!(pool.len(), 4, "`TypePool::retain` kept wrong values",);
+
+    let drained = pool.drain().collect::<Vec<_>>();
+    assert_eq!(drained.len(), 4, "`TypePool::drain` returned wrong values",);
+    assert!(pool.is_empty(), "`TypePool::drain` did not empty the pool",);
+    assert!(!pool.contains_key(&drained[0].0,), "`TypePool::drain` did not remove the values",);
+
+    let key = pool.insert(7,);
+    assert!(drained.iter().all(|(drained, _,),| *drained != key,), "`TypePool::insert` reissued a drained key",);
+    assert_eq!(pool.into_iter().collect::<Vec<_>>(), [(key, 7,)], "`TypePool::into_iter` returned wrong entries",);
+  }
 }
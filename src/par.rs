@@ -0,0 +1,188 @@
+//! Defines the parallel iterators over a [TypePool] enabled by the `rayon` feature.
+//! 
+//! Parallel iteration is supported by pools using the [SlabStorage] which stores its
+//! values contiguously.
+
+use crate::{
+  TypePool, PoolKey, PoolError,
+  storage::{PoolStorage, SlabStorage, Slot,},
+};
+use rayon::{prelude::*, iter::plumbing::UnindexedConsumer,};
+use std::{num::NonZeroUsize, marker::PhantomData,};
+
+/// A parallel iterator over the [PoolKey]s and values of a [TypePool].
+pub struct ParIter<'a, T,> {
+  slots: &'a [Slot<T,>],
+  pool_id: NonZeroUsize,
+}
+
+impl<'a, T,> ParallelIterator for ParIter<'a, T,>
+  where T: Send + Sync + 'a, {
+  type Item = (PoolKey<T,>, &'a T,);
+
+  fn drive_unindexed<C,>(self, consumer: C,) -> C::Result
+    where C: UnindexedConsumer<Self::Item>, {
+    let pool_id = self.pool_id;
+
+    self.slots.par_iter().enumerate()
+    .filter_map(move |(index, slot,),| match slot {
+      Slot::Occupied(generation, value,) => Some((PoolKey(index, pool_id, *generation, PhantomData,), value,)),
+      Slot::Vacant(_,) => None,
+    },)
+    .drive_unindexed(consumer,)
+  }
+}
+
+/// A parallel iterator over the [PoolKey]s and mutable values of a [TypePool].
+pub struct ParIterMut<'a, T,> {
+  slots: &'a mut [Slot<T,>],
+  pool_id: NonZeroUsize,
+}
+
+impl<'a, T,> ParallelIterator for ParIterMut<'a, T,>
+  where T: Send + 'a, {
+  type Item = (PoolKey<T,>, &'a mut T,);
+
+  fn drive_unindexed<C,>(self, consumer: C,) -> C::Result
+    where C: UnindexedConsumer<Self::Item>, {
+    let pool_id = self.pool_id;
+
+    self.slots.par_iter_mut().enumerate()
+    .filter_map(move |(index, slot,),| match slot {
+      Slot::Occupied(generation, value,) => Some((PoolKey(index, pool_id, *generation, PhantomData,), value,)),
+      Slot::Vacant(_,) => None,
+    },)
+    .drive_unindexed(consumer,)
+  }
+}
+
+/// A parallel iterator over the mutable values of a [TypePool].
+pub struct ParValuesMut<'a, T,>(&'a mut [Slot<T,>],);
+
+impl<'a, T,> ParallelIterator for ParValuesMut<'a, T,>
+  where T: Send + 'a, {
+  type Item = &'a mut T;
+
+  fn drive_unindexed<C,>(self, consumer: C,) -> C::Result
+    where C: UnindexedConsumer<Self::Item>, {
+    self.0.par_iter_mut()
+    .filter_map(|slot,| match slot {
+      Slot::Occupied(_, value,) => Some(value,),
+      Slot::Vacant(_,) => None,
+    },)
+    .drive_unindexed(consumer,)
+  }
+}
+
+impl<T,> TypePool<T, SlabStorage<T,>,> {
+  /// Returns a parallel iterator over the [PoolKey]s and values of this TypePool.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// use rayon::prelude::*;
+  /// 
+  /// let (pool, _,) = TypePool::from_iter(0..1000,);
+  /// 
+  /// assert_eq!(pool.par_iter().map(|(_, value,),| *value,).sum::<i32>(), 499500);
+  /// ```
+  #[inline]
+  pub fn par_iter(&self,) -> ParIter<'_, T,>
+    where T: Send + Sync, {
+    ParIter { slots: self.pool.slots(), pool_id: self.pool_id, }
+  }
+  /// Returns a parallel iterator over the [PoolKey]s and mutable values of this TypePool.
+  #[inline]
+  pub fn par_iter_mut(&mut self,) -> ParIterMut<'_, T,>
+    where T: Send, {
+    self.modified_all();
+    ParIterMut { slots: self.pool.slots_mut(), pool_id: self.pool_id, }
+  }
+  /// Returns a parallel iterator over the mutable values of this TypePool.
+  #[inline]
+  pub fn par_values_mut(&mut self,) -> ParValuesMut<'_, T,>
+    where T: Send, {
+    self.modified_all();
+    ParValuesMut(self.pool.slots_mut(),)
+  }
+  /// Removes every value for which `keep` returns `false`, calling `keep` in parallel.
+  /// 
+  /// `keep` may change the values so every kept value is recorded as modified.
+  /// 
+  /// # Params
+  /// 
+  /// keep --- Called with the [PoolKey] and value of every value.  
+  pub fn par_retain<F,>(&mut self, keep: F,)
+    where T: Send, F: Fn(PoolKey<T,>, &mut T,) -> bool + Send + Sync, {
+    let removed = ParIterMut { slots: self.pool.slots_mut(), pool_id: self.pool_id, }
+      .filter_map(|(key, value,),| if keep(key, value,) { None } else { Some(key,) },)
+      .collect::<Vec<_>>();
+
+    //The free list is not thread safe so the values are removed serially.
+    for key in removed { self.remove(key,); }
+    self.modified_all();
+  }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where T: Send, S: PoolStorage<T,>, {
+  /// Returns a parallel iterator over unique references too the values mapped too each of
+  /// `keys`.
+  /// 
+  /// The iterator is indexed so the reference at each position belongs to the key at the
+  /// same position of `keys`.
+  /// 
+  /// # Params
+  /// 
+  /// keys --- The distinct [PoolKey]s to get references too.  
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// use rayon::prelude::*;
+  /// 
+  /// let (mut pool, keys,) = TypePool::from_iter(0..10,);
+  /// 
+  /// pool.par_get_disjoint_mut(&keys[..5],).unwrap().for_each(|value,| *value *= 10,);
+  /// assert_eq!(pool[keys[4]], 40);
+  /// assert_eq!(pool[keys[5]], 5);
+  /// ```
+  pub fn par_get_disjoint_mut(&mut self, keys: &[PoolKey<T,>],) -> Result<rayon::vec::IntoIter<&mut T>, PoolError> {
+    self.get_disjoint_vec_mut(keys,).map(IntoParallelIterator::into_par_iter,)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_par_iter() {
+    let (mut pool, keys,) = TypePool::from_iter(0..1000,);
+
+    for &key in keys.iter().step_by(3,) { pool.remove(key,); }
+
+    let mut entries = pool.par_iter().map(|(key, value,),| (key.0, *value,),).collect::<Vec<_>>();
+    entries.sort_unstable();
+    assert_eq!(entries, pool.iter().map(|(key, value,),| (key.0, *value,),).collect::<Vec<_>>(), "`TypePool::par_iter` returned wrong entries",);
+
+    pool.par_iter_mut().for_each(|(key, value,),| *value += key.0 as i32,);
+    pool.par_values_mut().for_each(|value,| *value *= 2,);
+    assert_eq!(pool[keys[1]], 4, "`TypePool::par_values_mut` returned wrong values",);
+
+    pool.track_changes(true,);
+    pool.par_retain(|_, value,| *value % 4 == 0,);
+    assert!(pool.values().all(|value,| value % 4 == 0,), "`TypePool::par_retain` kept wrong values",);
+    assert!(!pool.contains_key(&keys[0],), "`TypePool::par_retain` revived a removed value",);
+    let changes = pool.take_changes();
+    assert_eq!(changes.modified(), &pool.keys().collect(), "`TypePool::par_retain` did not record kept values as modified",);
+    pool.track_changes(false,);
+
+    let live = pool.keys().take(10,).collect::<Vec<_>>();
+    let values = pool.par_get_disjoint_mut(&live,).unwrap().map(|value,| *value,).collect::<Vec<_>>();
+    assert_eq!(values, live.iter().map(|&key,| pool[key],).collect::<Vec<_>>(), "`TypePool::par_get_disjoint_mut` returned wrong order",);
+    assert_eq!(pool.par_get_disjoint_mut(&[live[0], live[0],],).err(), Some(PoolError::DuplicateKey), "`TypePool::par_get_disjoint_mut` accepted duplicates",);
+  }
+}
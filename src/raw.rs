@@ -0,0 +1,153 @@
+//! Defines the type erased [RawPoolKey].
+
+use crate::PoolKey;
+use std::{fmt, any::TypeId, num::NonZeroUsize, marker::PhantomData,};
+
+/// A [PoolKey] with its value type erased.
+/// 
+/// A RawPoolKey keeps the identity of the pool which issued it, its id and generation and,
+/// if the value type is `'static`, the `TypeId` of its value type. A RawPoolKey with a
+/// `TypeId` can only be downcast back to a PoolKey of that type.
+/// 
+/// RawPoolKeys are compared, ordered and hashed by their pool identity, id, generation and
+/// `TypeId`, so erasing the same key with and without its `TypeId` gives unequal
+/// RawPoolKeys. The `TypeId` keeps apart deserialized keys of different types, which all
+/// share one pool identity.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy,)]
+pub struct RawPoolKey {
+  pool_id: NonZeroUsize,
+  id: usize,
+  generation: usize,
+  type_id: Option<TypeId>,
+}
+
+impl RawPoolKey {
+  /// Returns the id of this RawPoolKey within its pool.
+  #[inline]
+  pub fn id(&self,) -> usize { self.id }
+  /// Returns the generation of the value of this RawPoolKey.
+  #[inline]
+  pub fn generation(&self,) -> usize { self.generation }
+  /// Returns the identity of the pool which issued this RawPoolKey.
+  #[inline]
+  pub fn pool_id(&self,) -> NonZeroUsize { self.pool_id }
+  /// Returns the `TypeId` of the value type of this RawPoolKey if it is known.
+  #[inline]
+  pub fn type_id(&self,) -> Option<TypeId> { self.type_id }
+  /// Returns `true` if this RawPoolKey is known to have been erased from a `PoolKey<T>`.
+  #[inline]
+  pub fn is<T,>(&self,) -> bool
+    where T: 'static, {
+    self.type_id == Some(TypeId::of::<T,>(),)
+  }
+  /// Returns this RawPoolKey as a `PoolKey<T>` or `None` if it was erased from a PoolKey
+  /// of another type.
+  /// 
+  /// Downcasting a RawPoolKey without a `TypeId` is unchecked and always succeeds. A pool
+  /// of another type reports the resulting PoolKey as foreign, but a deserialized key
+  /// belongs to no pool so nothing catches a wrong type.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, RawPoolKey,};
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key = pool.insert(1u32,);
+  /// let raw = RawPoolKey::from(key,);
+  /// 
+  /// assert_eq!(raw.downcast::<u32>(), Some(key));
+  /// assert_eq!(raw.downcast::<i32>(), None);
+  /// ```
+  pub fn downcast<T,>(self,) -> Option<PoolKey<T,>>
+    where T: 'static, {
+    match self.type_id {
+      Some(type_id,) if type_id != TypeId::of::<T,>() => None,
+      _ => Some(PoolKey(self.id, self.pool_id, self.generation, PhantomData,),),
+    }
+  }
+}
+
+impl<T,> PoolKey<T,> {
+  /// Returns this PoolKey as a [RawPoolKey] without the `TypeId` of `T`.
+  /// 
+  /// Unlike [PoolKey::erase] this works for value types which are not `'static`.
+  #[inline]
+  pub fn erase_untyped(self,) -> RawPoolKey {
+    RawPoolKey { pool_id: self.1, id: self.0, generation: self.2, type_id: None, }
+  }
+}
+
+impl<T,> PoolKey<T,>
+  where T: 'static, {
+  /// Returns this PoolKey as a [RawPoolKey] which remembers the `TypeId` of `T`.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key = pool.insert("value",);
+  /// let raw = key.erase();
+  /// 
+  /// assert!(raw.is::<&str>());
+  /// assert_eq!(raw.downcast::<&str>(), Some(key));
+  /// ```
+  #[inline]
+  pub fn erase(self,) -> RawPoolKey {
+    RawPoolKey { type_id: Some(TypeId::of::<T,>(),), ..self.erase_untyped() }
+  }
+}
+
+impl<T,> From<PoolKey<T,>> for RawPoolKey
+  where T: 'static, {
+  #[inline]
+  fn from(from: PoolKey<T,>,) -> Self { from.erase() }
+}
+
+impl fmt::Debug for RawPoolKey {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_struct("RawPoolKey",)
+    .field("id", &self.id,)
+    .field("generation", &self.generation,)
+    .field("pool", &self.pool_id,)
+    .field("type", &self.type_id,)
+    .finish()
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::TypePool;
+  use std::collections::{HashSet, BTreeSet,};
+
+  #[test]
+  fn test_raw_pool_key() {
+    let mut ints = TypePool::new();
+    let mut strs = TypePool::new();
+    let int = ints.insert(1,);
+    let other = ints.insert(2,);
+    let string = strs.insert("a",);
+
+    assert!(int.erase() != int.erase_untyped(), "`RawPoolKey::eq` ignored the type",);
+    assert!(int.erase() != other.erase(), "`RawPoolKey::eq` ignored the id",);
+    assert!(int.erase() < other.erase(), "`RawPoolKey::cmp` ignored the id",);
+    assert!(int.erase() < string.erase(), "`RawPoolKey::cmp` ignored the pool",);
+
+    //Keys of different types can share a pool identity, as deserialized keys do.
+    let pool_id = int.1;
+    assert!(PoolKey::<u8,>(0, pool_id, 0, PhantomData,).erase() != PoolKey::<i8,>(0, pool_id, 0, PhantomData,).erase(), "`RawPoolKey::eq` ignored the type",);
+
+    let keys = [string.erase(), int.erase(), other.erase_untyped(), int.erase_untyped(), int.erase(),];
+    assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 4, "`RawPoolKey::hash` is inconsistent with `eq`",);
+    assert_eq!(keys.iter().copied().collect::<BTreeSet<_>>().into_iter().collect::<Vec<_>>(), [int.erase_untyped(), int.erase(), other.erase_untyped(), string.erase(),], "`RawPoolKey::cmp` is wrong",);
+
+    assert_eq!(int.erase().downcast::<i32>(), Some(int), "`RawPoolKey::downcast` failed",);
+    assert_eq!(int.erase().downcast::<&str>(), None, "`RawPoolKey::downcast` ignored the type",);
+    assert_eq!(int.erase_untyped().type_id(), None, "`PoolKey::erase_untyped` kept the type",);
+    assert_eq!(strs.get(string.erase_untyped().downcast::<&str>().unwrap(),), Ok(&"a"), "`RawPoolKey::downcast` changed the key",);
+    assert!(!strs.owns_key(&int.erase_untyped().downcast::<&str>().unwrap(),), "`RawPoolKey::downcast` key was owned by another pool",);
+  }
+}
@@ -0,0 +1,289 @@
+//! Defines the [RcPool] which removes values once they are no longer referenced.
+
+use crate::{TypePool, PoolKey, PoolError, KeyState,};
+use std::{
+  ops, fmt,
+  cell::{Cell, RefCell,},
+  rc::{Rc, Weak,},
+};
+
+/// The keys whose last [StrongKey] has been dropped.
+type Released<T,> = RefCell<Vec<PoolKey<T,>>>;
+
+/// A value in an [RcPool] and the number of [StrongKey]s to it.
+struct Entry<T,> {
+  value: T,
+  strong: Rc<Cell<usize>>,
+}
+
+/// A pool of `T` values which are owned by the [StrongKey]s issued for them.
+/// 
+/// A value is removed once its last StrongKey is dropped; [WeakKey]s can refer to a value
+/// without keeping it alive. Removed values are dropped by [RcPool::purge], which
+/// [RcPool::insert] and [RcPool::get_mut] call first, so values may hold StrongKeys to
+/// other values in the same RcPool.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::RcPool;
+/// 
+/// let mut pool = RcPool::new();
+/// let strong = pool.insert(10,);
+/// let weak = strong.downgrade();
+/// 
+/// assert_eq!(pool[strong.key()], 10);
+/// assert!(weak.upgrade().is_some());
+/// 
+/// drop(strong,);
+/// assert!(weak.upgrade().is_none());
+/// assert!(!pool.contains_key(&weak.key(),));
+/// ```
+pub struct RcPool<T,> {
+  pool: TypePool<Entry<T,>,>,
+  released: Rc<Released<T,>>,
+}
+
+impl<T,> RcPool<T,> {
+  /// Returns a new empty RcPool.
+  #[inline]
+  pub fn new() -> Self {
+    Self { pool: TypePool::new(), released: Rc::default(), }
+  }
+  /// Drops the values whose last [StrongKey] has been dropped.
+  pub fn purge(&mut self,) {
+    //Dropping a value may release more values so the queue is not borrowed while dropping.
+    loop {
+      let key = self.released.borrow_mut().pop();
+
+      match key {
+        Some(key,) => drop(self.pool.remove(key.cast(),),),
+        None => break,
+      }
+    }
+  }
+  /// Returns `true` if `key` was issued by this RcPool.
+  #[inline]
+  pub fn owns_key(&self, key: &PoolKey<T,>,) -> bool { self.pool.owns_key(&key.cast(),) }
+  /// Returns the [KeyState] of `key` in this RcPool.
+  pub fn key_state(&self, key: &PoolKey<T,>,) -> KeyState {
+    match self.pool.key_state(&key.cast(),) {
+      KeyState::Live if self.pool[key.cast()].strong.get() == 0 => KeyState::Removed,
+      state => state,
+    }
+  }
+  /// Returns `true` if this RcPool contains `key`.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool { self.key_state(key,) == KeyState::Live }
+  /// Returns the number of values in this RcPool.
+  #[inline]
+  pub fn len(&self,) -> usize { self.pool.len() - self.released.borrow().len() }
+  /// Returns `true` if this RcPool is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Inserts `value` into the RcPool.
+  /// 
+  /// Returns the only [StrongKey] to the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the RcPool is full.
+  pub fn insert(&mut self, value: T,) -> StrongKey<T,> {
+    self.purge();
+
+    let strong = Rc::new(Cell::new(1,),);
+    let key = self.pool.insert(Entry { value, strong: strong.clone(), },).cast();
+
+    StrongKey { key, strong, released: Rc::downgrade(&self.released,), }
+  }
+  /// Returns `Ok` if `key` refers to a value in this RcPool.
+  fn check_key(&self, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+    match self.key_state(key,) {
+      KeyState::Live => Ok(()),
+      KeyState::Removed => Err(PoolError::StaleKey),
+      KeyState::Unknown if self.owns_key(key,) => Err(PoolError::MissingKey),
+      KeyState::Unknown => Err(PoolError::ForeignKey),
+    }
+  }
+  /// Returns a reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.  
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&T, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(&self.pool[key.cast()].value)
+  }
+  /// Returns a mutable reference to the value mapped too `key`.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.  
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut T, PoolError> {
+    self.purge();
+    self.check_key(&key,)?;
+
+    Ok(&mut self.pool[key.cast()].value)
+  }
+}
+
+impl<T,> Default for RcPool<T,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T,> ops::Index<PoolKey<T,>> for RcPool<T,> {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`RcPool::index` {}", e),)
+  }
+}
+
+impl<T,> ops::IndexMut<PoolKey<T,>> for RcPool<T,> {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`RcPool::index_mut` {}", e),)
+  }
+}
+
+/// A handle which keeps a value in an [RcPool] alive.
+pub struct StrongKey<T,> {
+  key: PoolKey<T,>,
+  strong: Rc<Cell<usize>>,
+  released: Weak<Released<T,>>,
+}
+
+impl<T,> StrongKey<T,> {
+  /// Returns the [PoolKey] of the value.
+  #[inline]
+  pub fn key(&self,) -> PoolKey<T,> { self.key }
+  /// Returns the number of StrongKeys to the value.
+  #[inline]
+  pub fn strong_count(&self,) -> usize { self.strong.get() }
+  /// Returns a [WeakKey] to the value.
+  #[inline]
+  pub fn downgrade(&self,) -> WeakKey<T,> {
+    WeakKey { key: self.key, strong: self.strong.clone(), released: self.released.clone(), }
+  }
+}
+
+impl<T,> Clone for StrongKey<T,> {
+  fn clone(&self,) -> Self {
+    self.strong.set(self.strong.get() + 1,);
+
+    Self { key: self.key, strong: self.strong.clone(), released: self.released.clone(), }
+  }
+}
+
+impl<T,> Drop for StrongKey<T,> {
+  fn drop(&mut self,) {
+    let strong = self.strong.get() - 1;
+
+    self.strong.set(strong,);
+    if strong == 0 {
+      //The RcPool may already have been dropped along with the value.
+      if let Some(released,) = self.released.upgrade() { released.borrow_mut().push(self.key,) }
+    }
+  }
+}
+
+impl<T,> fmt::Debug for StrongKey<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_tuple("StrongKey",).field(&self.key,).finish()
+  }
+}
+
+impl<T,> From<&StrongKey<T,>> for PoolKey<T,> {
+  #[inline]
+  fn from(from: &StrongKey<T,>,) -> Self { from.key }
+}
+
+/// A handle to a value in an [RcPool] which does not keep the value alive.
+pub struct WeakKey<T,> {
+  key: PoolKey<T,>,
+  strong: Rc<Cell<usize>>,
+  released: Weak<Released<T,>>,
+}
+
+impl<T,> WeakKey<T,> {
+  /// Returns the [PoolKey] of the value.
+  #[inline]
+  pub fn key(&self,) -> PoolKey<T,> { self.key }
+  /// Returns a [StrongKey] to the value or `None` if the value has been removed.
+  pub fn upgrade(&self,) -> Option<StrongKey<T,>> {
+    if self.strong.get() == 0 || self.released.strong_count() == 0 { return None }
+
+    self.strong.set(self.strong.get() + 1,);
+    Some(StrongKey { key: self.key, strong: self.strong.clone(), released: self.released.clone(), })
+  }
+}
+
+impl<T,> Clone for WeakKey<T,> {
+  fn clone(&self,) -> Self {
+    Self { key: self.key, strong: self.strong.clone(), released: self.released.clone(), }
+  }
+}
+
+impl<T,> fmt::Debug for WeakKey<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_tuple("WeakKey",).field(&self.key,).finish()
+  }
+}
+
+impl<T,> From<&WeakKey<T,>> for PoolKey<T,> {
+  #[inline]
+  fn from(from: &WeakKey<T,>,) -> Self { from.key }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  struct Node { next: Option<StrongKey<Node,>>, _count: Rc<()>, }
+
+  #[test]
+  fn test_rc_pool() {
+    let mut pool = RcPool::new();
+    let strong = pool.insert(1,);
+    let clone = strong.clone();
+    let weak = strong.downgrade();
+
+    assert_eq!(strong.strong_count(), 2, "`StrongKey::clone` did not count the clone",);
+    drop(strong,);
+    assert_eq!(pool[PoolKey::from(&weak,)], 1, "`RcPool::index` returned wrong value",);
+    assert_eq!(weak.upgrade().map(|key,| key.key(),), Some(clone.key()), "`WeakKey::upgrade` failed",);
+
+    drop(clone,);
+    assert!(weak.upgrade().is_none(), "`WeakKey::upgrade` revived a removed value",);
+    assert_eq!(pool.get(weak.key(),), Err(PoolError::StaleKey), "`RcPool::get` returned a removed value",);
+    assert_eq!(pool.len(), 0, "`RcPool::len` counted a removed value",);
+  }
+  #[test]
+  fn test_rc_pool_chain() {
+    let count = Rc::new((),);
+    let mut pool = RcPool::new();
+    let tail = pool.insert(Node { next: None, _count: count.clone(), },);
+    let head = pool.insert(Node { next: Some(tail,), _count: count.clone(), },);
+    let weak = pool[head.key()].next.as_ref().unwrap().downgrade();
+
+    drop(head,);
+    pool.purge();
+    assert!(weak.upgrade().is_none(), "`RcPool::purge` did not release the chain",);
+    assert_eq!(Rc::strong_count(&count,), 1, "`RcPool::purge` leaked values",);
+
+    let other = pool.insert(Node { next: None, _count: count.clone(), },);
+    drop(pool.insert(Node { next: None, _count: count.clone(), },),);
+    pool[other.key()].next = None;
+    assert_eq!(Rc::strong_count(&count,), 2, "`RcPool::get_mut` did not purge",);
+    drop(other,);
+
+    let strong = pool.insert(Node { next: None, _count: count.clone(), },);
+    let weak = strong.downgrade();
+    drop(pool,);
+    assert_eq!(Rc::strong_count(&count,), 1, "`RcPool::drop` leaked values",);
+    assert!(weak.upgrade().is_none(), "`WeakKey::upgrade` outlived the pool",);
+  }
+}
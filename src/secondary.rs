@@ -0,0 +1,385 @@
+//! Defines the [SecondaryMap] and [SparseSecondaryMap] which attach extra values to the
+//! [PoolKey]s of a [TypePool].
+//! 
+//! A secondary map is bound to the TypePool of the first key inserted into it and rejects
+//! keys from any other pool. Each entry remembers the generation of its key, so a key to
+//! a removed value never reaches the entry of a value inserted after it.
+
+use crate::{TypePool, PoolKey, PoolError, storage::PoolStorage,};
+use std::{ops, mem, num::NonZeroUsize, marker::PhantomData, collections::HashMap,};
+
+/// Returns `Ok` if `key` was issued by the pool a map is bound to.
+#[inline]
+fn check_pool<T,>(pool_id: Option<NonZeroUsize>, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+  match pool_id {
+    Some(pool_id,) if pool_id != key.1 => Err(PoolError::ForeignKey),
+    _ => Ok(()),
+  }
+}
+
+/// Returns `Ok` if an entry with `generation` belongs to `key`.
+#[inline]
+fn check_generation<T,>(generation: Option<usize>, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+  match generation {
+    Some(generation,) if generation == key.2 => Ok(()),
+    //Generations only increase so a newer entry means `key` has been removed.
+    Some(generation,) if generation > key.2 => Err(PoolError::StaleKey),
+    _ => Err(PoolError::MissingKey),
+  }
+}
+
+/// A map from the [PoolKey]s of a [TypePool] to `V` values.
+/// 
+/// The values are stored at the same index as their key's value in the TypePool, so
+/// every access is `O(1)`. The SecondaryMap grows to the largest index it holds, so a
+/// [SparseSecondaryMap] is better suited to pools whose storage issues large indices or
+/// when only a few keys have values.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::{TypePool, SecondaryMap, PoolError,};
+/// 
+/// let mut pool = TypePool::new();
+/// let mut names = SecondaryMap::new();
+/// let key = pool.insert(10,);
+/// 
+/// names.insert(key, "ten",);
+/// assert_eq!(names[key], "ten");
+/// 
+/// pool.remove(key,);
+/// names.retain_live(&pool,);
+/// assert_eq!(names.get(key,), Err(PoolError::MissingKey));
+/// ```
+pub struct SecondaryMap<T, V,> {
+  slots: Vec<Option<(usize, V,)>>,
+  len: usize,
+  pool_id: Option<NonZeroUsize>,
+  _keys: PhantomData<PoolKey<T,>>,
+}
+
+impl<T, V,> SecondaryMap<T, V,> {
+  /// Returns a new empty SecondaryMap.
+  #[inline]
+  pub const fn new() -> Self {
+    Self { slots: Vec::new(), len: 0, pool_id: None, _keys: PhantomData, }
+  }
+  /// Returns the number of values in this SecondaryMap.
+  #[inline]
+  pub fn len(&self,) -> usize { self.len }
+  /// Returns `true` if this SecondaryMap is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len == 0 }
+  /// Returns `Ok` if `key` has a value in this SecondaryMap.
+  fn check_key(&self, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+    check_pool(self.pool_id, key,)?;
+    check_generation(self.slots.get(key.0,).and_then(Option::as_ref,).map(|&(generation, _,),| generation,), key,)
+  }
+  /// Returns `true` if `key` has a value in this SecondaryMap.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool { self.check_key(key,).is_ok() }
+  /// Inserts `value` for `key` and returns the previous value of `key`.
+  /// 
+  /// The SecondaryMap cannot see the TypePool, so it only rejects a key removed from its
+  /// pool once a newer key with the same index has a value; use
+  /// [SecondaryMap::retain_live] to drop the values of removed keys.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is from another pool, is older than the key with a value at its index or its
+  /// index cannot be allocated.
+  pub fn insert(&mut self, key: PoolKey<T,>, value: V,) -> Option<V> {
+    self.try_insert(key, value,).unwrap_or_else(|(e, _,),| panic!("`SecondaryMap::insert` {}", e),)
+  }
+  /// Attempts to insert `value` for `key`.
+  /// 
+  /// Returns the previous value of `key` or `value` if `key` is from another pool, is older
+  /// than the key with a value at its index or its index cannot be allocated.
+  pub fn try_insert(&mut self, key: PoolKey<T,>, value: V,) -> Result<Option<V>, (PoolError, V,)> {
+    if let Err(e,) = check_pool(self.pool_id, &key,) { return Err((e, value,)) }
+    if key.0 >= self.slots.len() {
+      //The slots are reserved fallibly so a huge index is an error rather than an abort.
+      let len = match key.0.checked_add(1,) {
+        Some(len,) if self.slots.try_reserve(len - self.slots.len(),).is_ok() => len,
+        _ => return Err((PoolError::CapacityExhausted, value,)),
+      };
+
+      self.slots.resize_with(len, || None,)
+    }
+
+    let slot = &mut self.slots[key.0];
+    let previous = match slot {
+      Some((generation, _,),) if *generation > key.2 => return Err((PoolError::StaleKey, value,)),
+      Some((generation, _,),) if *generation == key.2 => slot.replace((key.2, value,),).map(|(_, value,),| value,),
+      //The entry belongs to a removed value and is replaced.
+      Some(_,) => { *slot = Some((key.2, value,),); None },
+      None => { *slot = Some((key.2, value,),); self.len += 1; None },
+    };
+
+    self.pool_id = Some(key.1,);
+    Ok(previous)
+  }
+  /// Removes the value of `key`.
+  /// 
+  /// Returns `None` if `key` has no value in this SecondaryMap.
+  #[inline]
+  pub fn remove(&mut self, key: PoolKey<T,>,) -> Option<V> { self.try_remove(key,).ok() }
+  /// Attempts to remove the value of `key`.
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<V, PoolError> {
+    self.check_key(&key,)?;
+
+    self.len -= 1;
+    Ok(self.slots[key.0].take().unwrap().1)
+  }
+  /// Returns a reference to the value of `key`.
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&V, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(&self.slots[key.0].as_ref().unwrap().1)
+  }
+  /// Returns a mutable reference to the value of `key`.
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut V, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(&mut self.slots[key.0].as_mut().unwrap().1)
+  }
+  /// Removes every value.
+  #[inline]
+  pub fn clear(&mut self,) {
+    self.slots.clear();
+    self.len = 0;
+  }
+  /// Removes every value for which `keep` returns `false`.
+  /// 
+  /// # Params
+  /// 
+  /// keep --- Called with the [PoolKey] and value of every value.  
+  pub fn retain<F,>(&mut self, mut keep: F,)
+    where F: FnMut(PoolKey<T,>, &mut V,) -> bool, {
+    let pool_id = match self.pool_id { Some(pool_id,) => pool_id, None => return, };
+
+    for (index, slot,) in self.slots.iter_mut().enumerate() {
+      if let Some((generation, value,),) = slot {
+        if !keep(PoolKey(index, pool_id, *generation, PhantomData,), value,) {
+          *slot = None;
+          self.len -= 1;
+        }
+      }
+    }
+  }
+  /// Removes every value whose key is no longer in `pool`.
+  pub fn retain_live<S,>(&mut self, pool: &TypePool<T, S,>,)
+    where S: PoolStorage<T,>, {
+    self.retain(|key, _,| pool.contains_key(&key,),)
+  }
+}
+
+impl<T, V,> Default for SecondaryMap<T, V,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T, V,> ops::Index<PoolKey<T,>> for SecondaryMap<T, V,> {
+  type Output = V;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`SecondaryMap::index` {}", e),)
+  }
+}
+
+impl<T, V,> ops::IndexMut<PoolKey<T,>> for SecondaryMap<T, V,> {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`SecondaryMap::index_mut` {}", e),)
+  }
+}
+
+/// A map from the [PoolKey]s of a [TypePool] to `V` values which only stores the keys
+/// with values.
+/// 
+/// Every access is a hash lookup of the index of the key.
+pub struct SparseSecondaryMap<T, V,> {
+  slots: HashMap<usize, (usize, V,)>,
+  pool_id: Option<NonZeroUsize>,
+  _keys: PhantomData<PoolKey<T,>>,
+}
+
+impl<T, V,> SparseSecondaryMap<T, V,> {
+  /// Returns a new empty SparseSecondaryMap.
+  #[inline]
+  pub fn new() -> Self {
+    Self { slots: HashMap::new(), pool_id: None, _keys: PhantomData, }
+  }
+  /// Returns the number of values in this SparseSecondaryMap.
+  #[inline]
+  pub fn len(&self,) -> usize { self.slots.len() }
+  /// Returns `true` if this SparseSecondaryMap is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.slots.is_empty() }
+  /// Returns `Ok` if `key` has a value in this SparseSecondaryMap.
+  fn check_key(&self, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+    check_pool(self.pool_id, key,)?;
+    check_generation(self.slots.get(&key.0,).map(|&(generation, _,),| generation,), key,)
+  }
+  /// Returns `true` if `key` has a value in this SparseSecondaryMap.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool { self.check_key(key,).is_ok() }
+  /// Inserts `value` for `key` and returns the previous value of `key`.
+  /// 
+  /// Like a [SecondaryMap] it only rejects a key removed from its pool once a newer key
+  /// with the same index has a value.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is from another pool or is older than the key with a value at its index.
+  pub fn insert(&mut self, key: PoolKey<T,>, value: V,) -> Option<V> {
+    self.try_insert(key, value,).unwrap_or_else(|(e, _,),| panic!("`SparseSecondaryMap::insert` {}", e),)
+  }
+  /// Attempts to insert `value` for `key`.
+  /// 
+  /// Returns the previous value of `key` or `value` if `key` is from another pool or is
+  /// older than the key with a value at its index.
+  pub fn try_insert(&mut self, key: PoolKey<T,>, value: V,) -> Result<Option<V>, (PoolError, V,)> {
+    if let Err(e,) = check_pool(self.pool_id, &key,) { return Err((e, value,)) }
+
+    let previous = match self.slots.get_mut(&key.0,) {
+      Some((generation, _,),) if *generation > key.2 => return Err((PoolError::StaleKey, value,)),
+      Some(slot,) => {
+        let (generation, previous,) = mem::replace(slot, (key.2, value,),);
+
+        //The entry of a removed value is replaced.
+        if generation == key.2 { Some(previous,) } else { None }
+      },
+      None => { self.slots.insert(key.0, (key.2, value,),); None },
+    };
+
+    self.pool_id = Some(key.1,);
+    Ok(previous)
+  }
+  /// Removes the value of `key`.
+  /// 
+  /// Returns `None` if `key` has no value in this SparseSecondaryMap.
+  #[inline]
+  pub fn remove(&mut self, key: PoolKey<T,>,) -> Option<V> { self.try_remove(key,).ok() }
+  /// Attempts to remove the value of `key`.
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<V, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(self.slots.remove(&key.0,).unwrap().1)
+  }
+  /// Returns a reference to the value of `key`.
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&V, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(&self.slots[&key.0].1)
+  }
+  /// Returns a mutable reference to the value of `key`.
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut V, PoolError> {
+    self.check_key(&key,)?;
+
+    Ok(&mut self.slots.get_mut(&key.0,).unwrap().1)
+  }
+  /// Removes every value.
+  #[inline]
+  pub fn clear(&mut self,) { self.slots.clear() }
+  /// Removes every value for which `keep` returns `false`.
+  /// 
+  /// # Params
+  /// 
+  /// keep --- Called with the [PoolKey] and value of every value.  
+  pub fn retain<F,>(&mut self, mut keep: F,)
+    where F: FnMut(PoolKey<T,>, &mut V,) -> bool, {
+    let pool_id = match self.pool_id { Some(pool_id,) => pool_id, None => return, };
+
+    self.slots.retain(|&index, (generation, value,),| keep(PoolKey(index, pool_id, *generation, PhantomData,), value,),)
+  }
+  /// Removes every value whose key is no longer in `pool`.
+  pub fn retain_live<S,>(&mut self, pool: &TypePool<T, S,>,)
+    where S: PoolStorage<T,>, {
+    self.retain(|key, _,| pool.contains_key(&key,),)
+  }
+}
+
+impl<T, V,> Default for SparseSecondaryMap<T, V,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T, V,> ops::Index<PoolKey<T,>> for SparseSecondaryMap<T, V,> {
+  type Output = V;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`SparseSecondaryMap::index` {}", e),)
+  }
+}
+
+impl<T, V,> ops::IndexMut<PoolKey<T,>> for SparseSecondaryMap<T, V,> {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`SparseSecondaryMap::index_mut` {}", e),)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_secondary_map() {
+    let mut pool = TypePool::new();
+    let mut map = SecondaryMap::new();
+    let key1 = pool.insert(1,);
+    let key2 = pool.insert(2,);
+
+    assert_eq!(map.insert(key1, 'a',), None, "`SecondaryMap::insert` returned a value for a new key",);
+    assert_eq!(map.insert(key1, 'b',), Some('a',), "`SecondaryMap::insert` did not replace the value",);
+    assert_eq!(map.get(key2,), Err(PoolError::MissingKey), "`SecondaryMap::get` returned a missing value",);
+    assert_eq!(map.try_insert(TypePool::new().insert(3,), 'c',), Err((PoolError::ForeignKey, 'c',)), "`SecondaryMap::try_insert` accepted a foreign key",);
+
+    pool.remove(key1,);
+    let key3 = pool.insert(3,);
+    assert_eq!(key3.0, key1.0, "`TypePool::insert` did not reuse the slot",);
+    map.insert(key3, 'd',);
+    assert_eq!(map.len(), 1, "`SecondaryMap::insert` did not replace the stale value",);
+    assert_eq!(map.get(key1,), Err(PoolError::StaleKey), "`SecondaryMap::get` accepted a stale key",);
+    assert_eq!(map.try_insert(key1, 'e',), Err((PoolError::StaleKey, 'e',)), "`SecondaryMap::try_insert` accepted a stale key",);
+
+    map.insert(key2, 'f',);
+    pool.remove(key2,);
+    map.retain_live(&pool,);
+    assert!(!map.contains_key(&key2,), "`SecondaryMap::retain_live` kept a removed key",);
+    assert_eq!(map[key3], 'd', "`SecondaryMap::retain_live` removed a live key",);
+
+    for &id in [usize::MAX, usize::MAX / 2,].iter() {
+      let huge = PoolKey(id, key3.1, 0, PhantomData,);
+      assert_eq!(map.try_insert(huge, 'g',), Err((PoolError::CapacityExhausted, 'g',)), "`SecondaryMap::try_insert` accepted an unallocatable index",);
+    }
+    assert_eq!(map.len(), 1, "`SecondaryMap::try_insert` changed the map on failure",);
+  }
+  #[test]
+  fn test_sparse_secondary_map() {
+    let mut pool = TypePool::new();
+    let mut map = SparseSecondaryMap::new();
+    let key1 = pool.insert(1,);
+    let key2 = pool.insert(2,);
+
+    assert_eq!(map.insert(key1, 'a',), None, "`SparseSecondaryMap::insert` returned a value for a new key",);
+    assert_eq!(map.insert(key1, 'b',), Some('a',), "`SparseSecondaryMap::insert` did not replace the value",);
+    assert_eq!(map.get(key2,), Err(PoolError::MissingKey), "`SparseSecondaryMap::get` returned a missing value",);
+    assert_eq!(map.try_insert(TypePool::new().insert(3,), 'c',), Err((PoolError::ForeignKey, 'c',)), "`SparseSecondaryMap::try_insert` accepted a foreign key",);
+
+    pool.remove(key1,);
+    let key3 = pool.insert(3,);
+    map.insert(key3, 'd',);
+    assert_eq!(map.len(), 1, "`SparseSecondaryMap::insert` did not replace the stale value",);
+    assert_eq!(map.get(key1,), Err(PoolError::StaleKey), "`SparseSecondaryMap::get` accepted a stale key",);
+
+    map.insert(key2, 'f',);
+    pool.remove(key2,);
+    map.retain_live(&pool,);
+    assert!(!map.contains_key(&key2,), "`SparseSecondaryMap::retain_live` kept a removed key",);
+    assert_eq!(map[key3], 'd', "`SparseSecondaryMap::retain_live` removed a live key",);
+  }
+}
@@ -0,0 +1,186 @@
+//! Defines the `serde` support for [TypePool] and [PoolKey].
+//! 
+//! A [TypePool] is serialized as a sequence of `(id, generation, value)` entries and a
+//! [PoolKey] as its `(id, generation)`. Deserializing always creates a new TypePool with
+//! a new identity, so stored keys must be translated using the [KeyRemap] returned by
+//! [TypePool::deserialize_with_remap].
+
+use crate::{TypePool, PoolKey, storage::PoolStorage,};
+use serde::{
+  Serialize, Serializer, Deserialize, Deserializer,
+  de::{Visitor, SeqAccess,},
+};
+use std::{fmt, cmp, num::NonZeroUsize, marker::PhantomData, collections::HashMap,};
+
+/// The most entries preallocated for a deserialized [TypePool], so a hostile size hint
+/// cannot exhaust memory.
+const MAX_PREALLOCATION: usize = 4096;
+
+/// The identity given to deserialized [PoolKey]s, which is never issued to a [TypePool].
+const UNBOUND_POOL_ID: NonZeroUsize = match NonZeroUsize::new(usize::MAX,) {
+  Some(id,) => id,
+  None => unreachable!(),
+};
+
+impl<T,> Serialize for PoolKey<T,> {
+  fn serialize<Ser,>(&self, serializer: Ser,) -> Result<Ser::Ok, Ser::Error>
+    where Ser: Serializer, {
+    (self.0, self.2,).serialize(serializer,)
+  }
+}
+
+/// Deserialized keys do not belong to any [TypePool] until translated by a [KeyRemap].
+impl<'de, T,> Deserialize<'de> for PoolKey<T,> {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    let (id, generation,) = <(usize, usize,)>::deserialize(deserializer,)?;
+
+    Ok(PoolKey(id, UNBOUND_POOL_ID, generation, PhantomData,))
+  }
+}
+
+impl<T, S,> Serialize for TypePool<T, S,>
+  where T: Serialize, S: PoolStorage<T,>, {
+  fn serialize<Ser,>(&self, serializer: Ser,) -> Result<Ser::Ok, Ser::Error>
+    where Ser: Serializer, {
+    serializer.collect_seq(self.pool.iter(),)
+  }
+}
+
+impl<'de, T, S,> Deserialize<'de> for TypePool<T, S,>
+  where T: Deserialize<'de>, S: PoolStorage<T,>, {
+  fn deserialize<D,>(deserializer: D,) -> Result<Self, D::Error>
+    where D: Deserializer<'de>, {
+    Self::deserialize_with_remap(deserializer,).map(|(pool, _,),| pool,)
+  }
+}
+
+/// A table which translates the [PoolKey]s of a serialized [TypePool] into the keys of
+/// the deserialized TypePool.
+pub struct KeyRemap<T,> {
+  keys: HashMap<(usize, usize,), PoolKey<T,>>,
+}
+
+impl<T,> KeyRemap<T,> {
+  /// Returns the key in the deserialized [TypePool] of the value `key` referred to.
+  /// 
+  /// Returns `None` if `key` did not refer to a serialized value.
+  /// 
+  /// # Params
+  /// 
+  /// key --- A key of the serialized TypePool.  
+  #[inline]
+  pub fn get(&self, key: PoolKey<T,>,) -> Option<PoolKey<T,>> {
+    self.keys.get(&(key.0, key.2,),).cloned()
+  }
+  /// Returns the number of translated keys.
+  #[inline]
+  pub fn len(&self,) -> usize { self.keys.len() }
+  /// Returns `true` if there are no translated keys.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.keys.is_empty() }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Deserializes a TypePool and returns it with a [KeyRemap] for the [PoolKey]s of the
+  /// serialized TypePool.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key = pool.insert("a".to_owned(),);
+  /// let saved_pool = serde_json::to_string(&pool,).unwrap();
+  /// let saved_key = serde_json::to_string(&key,).unwrap();
+  /// 
+  /// let (pool, remap,) = TypePool::<String>::deserialize_with_remap(
+  ///   &mut serde_json::Deserializer::from_str(&saved_pool,),
+  /// ).unwrap();
+  /// let key = remap.get(serde_json::from_str(&saved_key,).unwrap(),).unwrap();
+  /// 
+  /// assert_eq!(pool[key], "a");
+  /// ```
+  pub fn deserialize_with_remap<'de, D,>(deserializer: D,) -> Result<(Self, KeyRemap<T,>,), D::Error>
+    where T: Deserialize<'de>, D: Deserializer<'de>, {
+    /// Inserts the deserialized entries into a new [TypePool].
+    struct PoolVisitor<T, S,>(PhantomData<(T, S,)>,);
+
+    impl<'de, T, S,> Visitor<'de> for PoolVisitor<T, S,>
+      where T: Deserialize<'de>, S: PoolStorage<T,>, {
+      type Value = (TypePool<T, S,>, KeyRemap<T,>,);
+
+      fn expecting(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+        fmt.write_str("a sequence of `TypePool` entries",)
+      }
+      fn visit_seq<A,>(self, mut seq: A,) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>, {
+        use serde::de::Error;
+
+        let cap = cmp::min(seq.size_hint().unwrap_or(0,), MAX_PREALLOCATION,);
+        let mut pool = TypePool::with_capacity_in(cap,);
+        let mut keys = HashMap::with_capacity(cap,);
+
+        while let Some((id, generation, value,),) = seq.next_element::<(usize, usize, T,)>()? {
+          let key = pool.try_insert(value,).map_err(|(e, _,),| A::Error::custom(e,),)?;
+
+          if keys.insert((id, generation,), key,).is_some() {
+            return Err(A::Error::custom("duplicate `TypePool` entry",))
+          }
+        }
+
+        Ok((pool, KeyRemap { keys, },))
+      }
+    }
+
+    deserializer.deserialize_seq(PoolVisitor(PhantomData,),)
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::storage::HashStorage;
+
+  #[test]
+  fn test_serde() {
+    let (mut pool, keys,) = TypePool::from_iter(vec!["a", "b", "c",],);
+
+    pool.remove(keys[1],);
+    let json = serde_json::to_string(&pool,).expect("`TypePool::serialize` failed",);
+    let stored = serde_json::to_string(&keys,).expect("`PoolKey::serialize` failed",);
+
+    let stored: Vec<PoolKey<&str,>> = serde_json::from_str(&stored,).expect("`PoolKey::deserialize` failed",);
+    let (new, remap,) = TypePool::<&str, HashStorage<_,>,>::deserialize_with_remap(&mut serde_json::Deserializer::from_str(&json,),)
+      .expect("`TypePool::deserialize_with_remap` failed",);
+
+    assert_eq!(remap.len(), 2, "`KeyRemap::len` is wrong",);
+    assert!(!new.owns_key(&stored[0],), "deserialized `PoolKey` belongs to a pool",);
+    assert_eq!(new[remap.get(stored[0],).expect("`KeyRemap::get` failed",)], "a", "`KeyRemap::get` returned wrong key",);
+    assert_eq!(new[remap.get(stored[2],).expect("`KeyRemap::get` failed",)], "c", "`KeyRemap::get` returned wrong key",);
+    assert!(remap.get(stored[1],).is_none(), "`KeyRemap::get` remapped a removed key",);
+
+    let new: TypePool<&str,> = serde_json::from_str(&json,).expect("`TypePool::deserialize` failed",);
+    assert_eq!(new.len(), 2, "`TypePool::deserialize` returned wrong values",);
+  }
+  #[test]
+  fn test_size_hint() {
+    use serde::de::value::{SeqDeserializer, Error,};
+
+    /// An empty sequence which claims to be huge.
+    struct Huge;
+
+    impl Iterator for Huge {
+      type Item = u8;
+
+      fn next(&mut self,) -> Option<Self::Item> { None }
+      fn size_hint(&self,) -> (usize, Option<usize>,) { (usize::MAX, Some(usize::MAX,),) }
+    }
+
+    let (pool, remap,) = TypePool::<u8,>::deserialize_with_remap(SeqDeserializer::<_, Error>::new(Huge,),)
+      .expect("`TypePool::deserialize_with_remap` failed",);
+    assert!(pool.is_empty() && remap.is_empty(), "`TypePool::deserialize_with_remap` returned values",);
+  }
+}
@@ -0,0 +1,174 @@
+//! Defines the read only [PoolSnapshot]s of a [TypePool] using the [CowStorage].
+
+use crate::{TypePool, PoolKey, PoolError, KeyState, storage::{CowStorage, CowIter,},};
+use std::{ops, num::NonZeroUsize, marker::PhantomData,};
+
+/// A read only copy of a [TypePool] as it was when the snapshot was taken.
+/// 
+/// A PoolSnapshot shares its values with the TypePool it was taken from, so taking and
+/// cloning snapshots is `O(1)`. Changing the TypePool afterwards only copies the chunks of
+/// values which are changed.
+/// 
+/// A PoolSnapshot resolves the [PoolKey]s of its TypePool to the values they had when the
+/// snapshot was taken.
+pub struct PoolSnapshot<T,> {
+  storage: CowStorage<T,>,
+  pool_id: NonZeroUsize,
+  next_generation: usize,
+}
+
+impl<T,> PoolSnapshot<T,> {
+  /// Returns `true` if `key` was issued by the [TypePool] of this PoolSnapshot.
+  #[inline]
+  pub fn owns_key(&self, key: &PoolKey<T,>,) -> bool { key.1 == self.pool_id }
+  /// Returns the [KeyState] of `key` when this PoolSnapshot was taken.
+  /// 
+  /// Keys issued after the snapshot was taken are [KeyState::Unknown].
+  pub fn key_state(&self, key: &PoolKey<T,>,) -> KeyState {
+    if !self.owns_key(key,) || key.2 >= self.next_generation { return KeyState::Unknown }
+
+    match self.storage.get(key.0,) {
+      Some((generation, _,)) if generation == key.2 => KeyState::Live,
+      _ => KeyState::Removed,
+    }
+  }
+  /// Returns `true` if this PoolSnapshot contains `key`.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool { self.key_state(key,) == KeyState::Live }
+  /// Returns a reference to the value mapped too `key` when this PoolSnapshot was taken.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get(&self, key: PoolKey<T,>,) -> Result<&T, PoolError> {
+    match self.key_state(&key,) {
+      KeyState::Live => Ok(self.storage.get(key.0,).unwrap().1),
+      KeyState::Removed => Err(PoolError::StaleKey),
+      KeyState::Unknown if self.owns_key(&key,) => Err(PoolError::MissingKey),
+      KeyState::Unknown => Err(PoolError::ForeignKey),
+    }
+  }
+  /// Returns the number of values in this PoolSnapshot.
+  #[inline]
+  pub fn len(&self,) -> usize { self.storage.len() }
+  /// Returns `true` if this PoolSnapshot is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Returns an iterator over the [PoolKey]s and values of this PoolSnapshot.
+  #[inline]
+  pub fn iter(&self,) -> SnapshotIter<'_, T,> {
+    SnapshotIter { iter: self.storage.iter(), pool_id: self.pool_id, }
+  }
+  /// Returns an iterator over the [PoolKey]s of this PoolSnapshot.
+  #[inline]
+  pub fn keys(&self,) -> SnapshotKeys<'_, T,> { SnapshotKeys(self.iter(),) }
+  /// Returns an iterator over the values of this PoolSnapshot.
+  #[inline]
+  pub fn values(&self,) -> SnapshotValues<'_, T,> { SnapshotValues(self.storage.iter(),) }
+}
+
+impl<T,> Clone for PoolSnapshot<T,> {
+  #[inline]
+  fn clone(&self,) -> Self {
+    Self { storage: self.storage.clone(), pool_id: self.pool_id, next_generation: self.next_generation, }
+  }
+}
+
+impl<T,> ops::Index<PoolKey<T,>> for PoolSnapshot<T,> {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output {
+    self.get(key,).unwrap_or_else(|e,| panic!("`PoolSnapshot::index` {}", e),)
+  }
+}
+
+/// An iterator over the [PoolKey]s and values of a [PoolSnapshot].
+pub struct SnapshotIter<'a, T,> {
+  iter: CowIter<'a, T,>,
+  pool_id: NonZeroUsize,
+}
+
+impl<'a, T,> Iterator for SnapshotIter<'a, T,> {
+  type Item = (PoolKey<T,>, &'a T,);
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.iter.next()
+    .map(|(index, generation, value,),| (PoolKey(index, self.pool_id, generation, PhantomData,), value,),)
+  }
+}
+
+/// An iterator over the [PoolKey]s of a [PoolSnapshot].
+pub struct SnapshotKeys<'a, T,>(SnapshotIter<'a, T,>,);
+
+impl<T,> Iterator for SnapshotKeys<'_, T,> {
+  type Item = PoolKey<T,>;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next().map(|(key, _,),| key,) }
+}
+
+/// An iterator over the values of a [PoolSnapshot].
+pub struct SnapshotValues<'a, T,>(CowIter<'a, T,>,);
+
+impl<'a, T,> Iterator for SnapshotValues<'a, T,> {
+  type Item = &'a T;
+
+  #[inline]
+  fn next(&mut self,) -> Option<Self::Item> { self.0.next().map(|(_, _, value,),| value,) }
+}
+
+impl<T,> TypePool<T, CowStorage<T,>,>
+  where T: Clone, {
+  /// Returns a read only snapshot of the current values of this TypePool in `O(1)`.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::{TypePool, storage::CowStorage,};
+  /// 
+  /// let mut pool = TypePool::<_, CowStorage<_>>::default();
+  /// let key = pool.insert(1,);
+  /// let snapshot = pool.snapshot();
+  /// 
+  /// pool[key] = 2;
+  /// assert_eq!(snapshot[key], 1);
+  /// assert_eq!(pool[key], 2);
+  /// ```
+  pub fn snapshot(&self,) -> PoolSnapshot<T,> {
+    PoolSnapshot { storage: self.pool.clone(), pool_id: self.pool_id, next_generation: self.next_generation, }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use std::thread;
+
+  #[test]
+  fn test_snapshot() {
+    let mut pool = TypePool::<_, CowStorage<_,>,>::default();
+    let keys = (0..100).map(|value,| pool.insert(value,),).collect::<Vec<_>>();
+    let snapshot = pool.snapshot();
+
+    pool.remove(keys[0],);
+    pool[keys[1]] = -1;
+    let key = pool.insert(100,);
+
+    let saved = thread::spawn({
+      let snapshot = snapshot.clone();
+      move || snapshot.values().sum::<i32>()
+    },);
+    assert_eq!(saved.join().unwrap(), 4950, "`PoolSnapshot` saw later changes",);
+
+    assert_eq!(snapshot.len(), 100, "`PoolSnapshot::len` is wrong",);
+    assert_eq!(snapshot.get(keys[0],), Ok(&0), "`PoolSnapshot::get` lost a removed value",);
+    assert_eq!(snapshot.get(keys[1],), Ok(&1), "`PoolSnapshot::get` saw a modification",);
+    assert_eq!(snapshot.get(key,), Err(PoolError::MissingKey), "`PoolSnapshot::get` saw a later insert",);
+    assert_eq!(pool.snapshot().get(keys[0],), Err(PoolError::StaleKey), "`PoolSnapshot::get` returned a removed value",);
+    assert_eq!(TypePool::<_, CowStorage<_,>,>::default().snapshot().get(key,), Err(PoolError::ForeignKey), "`PoolSnapshot::get` accepted a foreign key",);
+    assert!(snapshot.keys().eq(keys.iter().copied(),), "`PoolSnapshot::keys` returned wrong keys",);
+    assert!(snapshot.iter().map(|(_, &value,),| value,).eq(0..100,), "`PoolSnapshot::iter` returned wrong values",);
+  }
+}
@@ -0,0 +1,82 @@
+//! Defines a fixed capacity storage for [TypePool](crate::TypePool)s which keeps its values
+//! inline.
+//! 
+//! Storing values never allocates; only [PoolStorage::get_disjoint_mut] allocates the
+//! `Vec` it returns.
+
+use super::{PoolStorage, SlotIter, SlotIterMut, SlotIntoIter, slab::{self, Slot,},};
+
+/// A storage which keeps up to `N` values in an inline array.
+/// 
+/// Inserting into a full ArrayStorage fails with
+/// [PoolError::CapacityExhausted](crate::PoolError::CapacityExhausted).
+pub struct ArrayStorage<T, const N: usize,> {
+  /// The slots of the storage.
+  slots: [Slot<T,>; N],
+  /// The index of the first empty slot.
+  free: Option<usize>,
+  /// The number of occupied slots.
+  len: usize,
+}
+
+impl<T, const N: usize,> ArrayStorage<T, N,> {
+  /// Returns a new empty ArrayStorage.
+  pub fn new() -> Self {
+    Self {
+      slots: std::array::from_fn(|index,| Slot::Vacant(Some(index + 1,).filter(|&next,| next < N,),),),
+      free: Some(0,).filter(|_,| N > 0,),
+      len: 0,
+    }
+  }
+}
+
+impl<T, const N: usize,> Default for ArrayStorage<T, N,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T, const N: usize,> PoolStorage<T,> for ArrayStorage<T, N,> {
+  type Iter<'a,> = SlotIter<'a, T,>
+    where T: 'a;
+  type IterMut<'a,> = SlotIterMut<'a, T,>
+    where T: 'a;
+  type IntoEntries = SlotIntoIter<std::array::IntoIter<Slot<T,>, N,>>;
+
+  #[inline]
+  fn len(&self,) -> usize { self.len }
+  #[inline]
+  fn capacity(&self,) -> usize { N }
+  fn insert(&mut self, generation: usize, value: T,) -> Result<usize, T> {
+    if self.free.is_none() { return Err(value) }
+
+    self.len += 1;
+    Ok(slab::fill(&mut self.slots, &mut self.free, generation, value,))
+  }
+  fn insert_at(&mut self, index: usize, generation: usize, value: T,) -> Result<(), T> {
+    slab::fill_at(&mut self.slots, &mut self.free, index, generation, value,)?;
+    self.len += 1;
+    Ok(())
+  }
+  #[inline]
+  fn next_index(&self,) -> Option<usize> { self.free }
+  fn remove(&mut self, index: usize,) -> Option<(usize, T,)> {
+    let value = slab::vacate(&mut self.slots, &mut self.free, index,)?;
+
+    self.len -= 1;
+    Some(value,)
+  }
+  #[inline]
+  fn get(&self, index: usize,) -> Option<(usize, &T,)> { slab::get(&self.slots, index,) }
+  #[inline]
+  fn get_mut(&mut self, index: usize,) -> Option<(usize, &mut T,)> { slab::get_mut(&mut self.slots, index,) }
+  #[inline]
+  fn get_disjoint_mut(&mut self, indices: &[usize],) -> Option<Vec<&mut T>> {
+    slab::get_disjoint_mut(&mut self.slots, indices,)
+  }
+  #[inline]
+  fn iter(&self,) -> Self::Iter<'_,> { SlotIter::new(&self.slots,) }
+  #[inline]
+  fn iter_mut(&mut self,) -> Self::IterMut<'_,> { SlotIterMut::new(&mut self.slots,) }
+  #[inline]
+  fn into_entries(self,) -> Self::IntoEntries { SlotIntoIter::new(IntoIterator::into_iter(self.slots,),) }
+}
@@ -0,0 +1,111 @@
+//! Defines an ordered storage for [TypePool](crate::TypePool)s.
+
+use super::{PoolStorage, all_distinct,};
+use std::{iter::Map, collections::{BTreeMap, btree_map,},};
+
+/// Returns the index, generation and value of a map entry.
+fn entry<'a, T,>((index, (generation, value,),): (&'a usize, &'a (usize, T,),),) -> (usize, usize, &'a T,) {
+  (*index, *generation, value,)
+}
+
+/// Returns the index, generation and mutable value of a map entry.
+fn entry_mut<'a, T,>((index, (generation, value,),): (&'a usize, &'a mut (usize, T,),),) -> (usize, usize, &'a mut T,) {
+  (*index, *generation, value,)
+}
+
+/// Returns the index, generation and value of an owned map entry.
+fn into_entry<T,>((index, (generation, value,),): (usize, (usize, T,),),) -> (usize, usize, T,) {
+  (index, generation, value,)
+}
+
+/// A storage which keeps values in a `BTreeMap` ordered by their index.
+/// 
+/// Memory use is proportional to the number of values and values are visited in order of
+/// their index.
+pub struct BTreeStorage<T,> {
+  /// The values of the storage and their generations.
+  values: BTreeMap<usize, (usize, T,),>,
+  /// The index to begin searching for an unused index from.
+  next_id: usize,
+}
+
+impl<T,> BTreeStorage<T,> {
+  /// Returns a new empty BTreeStorage.
+  #[inline]
+  pub const fn new() -> Self {
+    Self { values: BTreeMap::new(), next_id: 0, }
+  }
+  /// Returns the next unused index in the storage.
+  fn find_next_id(&self,) -> usize {
+    (self.next_id..=usize::MAX)
+    .chain(0..self.next_id,)
+    .find(|key,| !self.values.contains_key(key,),)
+    .unwrap()
+  }
+}
+
+impl<T,> Default for BTreeStorage<T,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T,> PoolStorage<T,> for BTreeStorage<T,> {
+  type Iter<'a,> = Map<btree_map::Iter<'a, usize, (usize, T,),>, fn((&'a usize, &'a (usize, T,),),) -> (usize, usize, &'a T,)>
+    where T: 'a;
+  type IterMut<'a,> = Map<btree_map::IterMut<'a, usize, (usize, T,),>, fn((&'a usize, &'a mut (usize, T,),),) -> (usize, usize, &'a mut T,)>
+    where T: 'a;
+  type IntoEntries = Map<btree_map::IntoIter<usize, (usize, T,),>, fn((usize, (usize, T,),),) -> (usize, usize, T,)>;
+
+  #[inline]
+  fn len(&self,) -> usize { self.values.len() }
+  fn insert(&mut self, generation: usize, value: T,) -> Result<usize, T> {
+    if self.len() == usize::MAX { return Err(value) }
+
+    let id = self.find_next_id();
+
+    self.next_id = id.wrapping_add(1,);
+    self.values.insert(id, (generation, value,),);
+    Ok(id)
+  }
+  fn insert_at(&mut self, index: usize, generation: usize, value: T,) -> Result<(), T> {
+    if self.values.contains_key(&index,) { return Err(value) }
+
+    self.values.insert(index, (generation, value,),);
+    Ok(())
+  }
+  #[inline]
+  fn next_index(&self,) -> Option<usize> {
+    if self.len() == usize::MAX { None } else { Some(self.find_next_id(),) }
+  }
+  #[inline]
+  fn remove(&mut self, index: usize,) -> Option<(usize, T,)> { self.values.remove(&index,) }
+  #[inline]
+  fn get(&self, index: usize,) -> Option<(usize, &T,)> {
+    self.values.get(&index,).map(|(generation, value,),| (*generation, value,),)
+  }
+  #[inline]
+  fn get_mut(&mut self, index: usize,) -> Option<(usize, &mut T,)> {
+    self.values.get_mut(&index,).map(|(generation, value,),| (*generation, value,),)
+  }
+  /// Visits every value between the smallest and largest of `indices`.
+  fn get_disjoint_mut(&mut self, indices: &[usize],) -> Option<Vec<&mut T>> {
+    if !all_distinct(indices,) { return None }
+
+    let (&min, &max,) = match (indices.iter().min(), indices.iter().max(),) {
+      (Some(min,), Some(max,),) => (min, max,),
+      _ => return Some(Vec::new()),
+    };
+    let mut values = self.values.range_mut(min..=max,)
+      .filter(|(index, _,),| indices.contains(index,),)
+      .map(|(index, (_, value,),),| (*index, value,),)
+      .collect::<BTreeMap<_, _,>>();
+
+    indices.iter().map(|index,| values.remove(index,),).collect()
+  }
+  #[inline]
+  fn iter(&self,) -> Self::Iter<'_,> { self.values.iter().map(entry,) }
+  #[inline]
+  fn iter_mut(&mut self,) -> Self::IterMut<'_,> { self.values.iter_mut().map(entry_mut,) }
+  #[inline]
+  fn into_entries(self,) -> Self::IntoEntries { self.values.into_iter().map(into_entry,) }
+}
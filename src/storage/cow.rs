@@ -0,0 +1,343 @@
+//! Defines a chunked copy on write storage whose clones share their values.
+
+use super::{PoolStorage, SlotIter, SlotIterMut, SlotIntoIter, all_distinct, slab::Slot,};
+use std::{mem, slice, iter::Enumerate, sync::Arc,};
+
+/// The base two log of the number of slots in a chunk.
+const CHUNK_BITS: u32 = 6;
+/// The number of slots in a chunk.
+const CHUNK_LEN: usize = 1 << CHUNK_BITS;
+
+/// The shared slots of a [CowStorage].
+type Chunks<T,> = Arc<Vec<Arc<Vec<Slot<T,>>>>>;
+
+/// Returns the chunk holding `index` and the offset of `index` in that chunk.
+#[inline]
+fn locate(index: usize,) -> (usize, usize,) { (index >> CHUNK_BITS, index & (CHUNK_LEN - 1),) }
+
+/// An iterator over the occupied slots of a [CowStorage].
+pub struct CowIter<'a, T,> {
+  chunks: Enumerate<slice::Iter<'a, Arc<Vec<Slot<T,>>>>>,
+  /// The first index of the current chunk and its remaining slots.
+  slots: Option<(usize, SlotIter<'a, T,>,)>,
+}
+
+impl<'a, T,> Iterator for CowIter<'a, T,> {
+  type Item = (usize, usize, &'a T,);
+
+  fn next(&mut self,) -> Option<Self::Item> {
+    loop {
+      if let Some((base, slots,),) = &mut self.slots {
+        if let Some((index, generation, value,),) = slots.next() { return Some((*base + index, generation, value,)) }
+      }
+
+      let (chunk, slots,) = self.chunks.next()?;
+      self.slots = Some((chunk * CHUNK_LEN, SlotIter::new(slots,),),);
+    }
+  }
+}
+
+/// An iterator over the occupied slots of a [CowStorage] which yields mutable values.
+/// 
+/// Each chunk is copied as it is reached if it is shared.
+pub struct CowIterMut<'a, T,> {
+  chunks: Enumerate<slice::IterMut<'a, Arc<Vec<Slot<T,>>>>>,
+  /// The first index of the current chunk and its remaining slots.
+  slots: Option<(usize, SlotIterMut<'a, T,>,)>,
+}
+
+impl<'a, T,> Iterator for CowIterMut<'a, T,>
+  where T: Clone, {
+  type Item = (usize, usize, &'a mut T,);
+
+  fn next(&mut self,) -> Option<Self::Item> {
+    loop {
+      if let Some((base, slots,),) = &mut self.slots {
+        if let Some((index, generation, value,),) = slots.next() { return Some((*base + index, generation, value,)) }
+      }
+
+      let (chunk, slots,) = self.chunks.next()?;
+      self.slots = Some((chunk * CHUNK_LEN, SlotIterMut::new(Arc::make_mut(slots,).as_mut_slice(),),),);
+    }
+  }
+}
+
+/// A storage which keeps its slots in fixed size chunks shared between its clones.
+/// 
+/// Cloning a CowStorage is `O(1)`; the clones share their chunks until one of them
+/// changes a chunk, at which point only that chunk is copied. Values are only ever cloned
+/// when a shared chunk is copied.
+pub struct CowStorage<T,> {
+  /// The chunks of slots of the storage.
+  chunks: Chunks<T,>,
+  /// The index of the first empty slot.
+  free: Option<usize>,
+  /// The number of occupied slots.
+  len: usize,
+}
+
+impl<T,> CowStorage<T,> {
+  /// Returns a new empty CowStorage.
+  #[inline]
+  pub fn new() -> Self {
+    Self { chunks: Arc::new(Vec::new(),), free: None, len: 0, }
+  }
+  /// Returns the number of slots in this CowStorage.
+  fn slots(&self,) -> usize {
+    match self.chunks.last() {
+      Some(chunk,) => (self.chunks.len() - 1) * CHUNK_LEN + chunk.len(),
+      None => 0,
+    }
+  }
+  /// Returns the slot at `index`.
+  #[inline]
+  fn slot(&self, index: usize,) -> Option<&Slot<T,>> {
+    let (chunk, offset,) = locate(index,);
+
+    self.chunks.get(chunk,)?.get(offset,)
+  }
+  /// Returns the number of values in this CowStorage.
+  #[inline]
+  pub fn len(&self,) -> usize { self.len }
+  /// Returns `true` if this CowStorage is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len == 0 }
+  /// Returns the generation and value at `index`.
+  /// 
+  /// Unlike [PoolStorage::get] this does not need `T: Clone`.
+  pub fn get(&self, index: usize,) -> Option<(usize, &T,)> {
+    match self.slot(index,) {
+      Some(Slot::Occupied(generation, value,),) => Some((*generation, value,)),
+      _ => None,
+    }
+  }
+  /// Returns an iterator over the indices, generations and values of this CowStorage.
+  /// 
+  /// Unlike [PoolStorage::iter] this does not need `T: Clone`.
+  #[inline]
+  pub fn iter(&self,) -> CowIter<'_, T,> { CowIter { chunks: self.chunks.iter().enumerate(), slots: None, } }
+  /// Returns `true` if the value at `index` is shared with another CowStorage.
+  /// 
+  /// # Params
+  /// 
+  /// index --- The index of the value.
+  pub fn is_shared(&self, index: usize,) -> bool {
+    Arc::strong_count(&self.chunks,) > 1
+    || self.chunks.get(locate(index,).0,).is_some_and(|chunk,| Arc::strong_count(chunk,) > 1,)
+  }
+}
+
+impl<T,> CowStorage<T,>
+  where T: Clone, {
+  /// Returns the unshared slot at `index`, copying its chunk if it is shared.
+  fn slot_mut(&mut self, index: usize,) -> Option<&mut Slot<T,>> {
+    let (chunk, offset,) = locate(index,);
+
+    Arc::make_mut(Arc::make_mut(&mut self.chunks,).get_mut(chunk,)?,).get_mut(offset,)
+  }
+  /// Appends `slot` to the last chunk.
+  fn push(&mut self, slot: Slot<T,>,) {
+    let chunks = Arc::make_mut(&mut self.chunks,);
+
+    match chunks.last() {
+      Some(chunk,) if chunk.len() < CHUNK_LEN => {},
+      _ => chunks.push(Arc::new(Vec::with_capacity(CHUNK_LEN,),),),
+    }
+
+    Arc::make_mut(chunks.last_mut().unwrap(),).push(slot,);
+  }
+}
+
+impl<T,> Default for CowStorage<T,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T,> Clone for CowStorage<T,> {
+  #[inline]
+  fn clone(&self,) -> Self {
+    Self { chunks: Arc::clone(&self.chunks,), free: self.free, len: self.len, }
+  }
+}
+
+impl<T,> PoolStorage<T,> for CowStorage<T,>
+  where T: Clone, {
+  type Iter<'a,> = CowIter<'a, T,>
+    where T: 'a;
+  type IterMut<'a,> = CowIterMut<'a, T,>
+    where T: 'a;
+  type IntoEntries = std::vec::IntoIter<(usize, usize, T,)>;
+
+  #[inline]
+  fn len(&self,) -> usize { self.len }
+  #[inline]
+  fn capacity(&self,) -> usize { self.chunks.len() * CHUNK_LEN }
+  fn insert(&mut self, generation: usize, value: T,) -> Result<usize, T> {
+    if self.len == usize::MAX { return Err(value) }
+
+    let index = match self.free {
+      Some(index,) => {
+        self.free = match mem::replace(self.slot_mut(index,).unwrap(), Slot::Occupied(generation, value,),) {
+          Slot::Vacant(next,) => next,
+          Slot::Occupied(..) => unreachable!("`CowStorage::insert` free list contains an occupied slot",),
+        };
+        index
+      },
+      None => {
+        self.push(Slot::Occupied(generation, value,),);
+        self.slots() - 1
+      },
+    };
+
+    self.len += 1;
+    Ok(index)
+  }
+  fn insert_at(&mut self, index: usize, generation: usize, value: T,) -> Result<(), T> {
+    let next = match self.slot(index,) {
+      Some(Slot::Vacant(next,),) => *next,
+      Some(Slot::Occupied(..),) => return Err(value),
+      //Slots which were never filled are added to the free list.
+      None => {
+        while self.slots() <= index {
+          self.push(Slot::Vacant(self.free,),);
+          self.free = Some(self.slots() - 1,);
+        }
+
+        return self.insert_at(index, generation, value,)
+      },
+    };
+
+    //The slot is usually the head of the free list when values are restored in reverse.
+    if self.free == Some(index,) { self.free = next }
+    else {
+      let mut prev = self.free.expect("`CowStorage::insert_at` free list is corrupted",);
+
+      loop {
+        match self.slot(prev,) {
+          Some(Slot::Vacant(Some(link,),),) if *link == index => break,
+          Some(Slot::Vacant(Some(link,),),) => prev = *link,
+          _ => unreachable!("`CowStorage::insert_at` free list is corrupted",),
+        }
+      }
+      *self.slot_mut(prev,).unwrap() = Slot::Vacant(next,);
+    }
+
+    *self.slot_mut(index,).unwrap() = Slot::Occupied(generation, value,);
+    self.len += 1;
+    Ok(())
+  }
+  #[inline]
+  fn next_index(&self,) -> Option<usize> {
+    if self.len == usize::MAX { None }
+    else { Some(self.free.unwrap_or(self.slots(),),) }
+  }
+  fn remove(&mut self, index: usize,) -> Option<(usize, T,)> {
+    //Missing values are checked first so that nothing is copied.
+    if let Some(Slot::Vacant(_,),) | None = self.slot(index,) { return None }
+
+    let free = self.free.replace(index,);
+
+    match mem::replace(self.slot_mut(index,).unwrap(), Slot::Vacant(free,),) {
+      Slot::Occupied(generation, value,) => {
+        self.len -= 1;
+        Some((generation, value,))
+      },
+      Slot::Vacant(_,) => unreachable!(),
+    }
+  }
+  #[inline]
+  fn get(&self, index: usize,) -> Option<(usize, &T,)> { CowStorage::get(self, index,) }
+  fn get_mut(&mut self, index: usize,) -> Option<(usize, &mut T,)> {
+    if let Some(Slot::Vacant(_,),) | None = self.slot(index,) { return None }
+
+    match self.slot_mut(index,) {
+      Some(Slot::Occupied(generation, value,),) => Some((*generation, value,)),
+      _ => None,
+    }
+  }
+  fn get_disjoint_mut(&mut self, indices: &[usize],) -> Option<Vec<&mut T>> {
+    if !all_distinct(indices,) { return None }
+
+    let chunks = Arc::make_mut(&mut self.chunks,);
+    //Each chunk is unshared and borrowed once for its base pointer; the slots are then
+    //reached through that pointer so no later borrow of the chunk invalidates them.
+    let mut bases = Vec::<(usize, *mut Slot<T,>, usize,)>::new();
+    let slots = indices.iter()
+      .map(|&index,| {
+        let (chunk, offset,) = locate(index,);
+        let (base, len,) = match bases.iter().find(|&&(base_chunk, _, _,),| base_chunk == chunk,) {
+          Some(&(_, base, len,),) => (base, len,),
+          None => {
+            let slots = Arc::make_mut(chunks.get_mut(chunk,)?,);
+            let (base, len,) = (slots.as_mut_ptr(), slots.len(),);
+
+            bases.push((chunk, base, len,),);
+            (base, len,)
+          },
+        };
+
+        if offset >= len { return None }
+        //`offset` is within the chunk.
+        Some(unsafe { base.add(offset,) },)
+      },)
+      .collect::<Option<Vec<_>>>()?;
+
+    slots.into_iter()
+    //The indices are distinct so the references do not alias.
+    .map(|slot,| match unsafe { &mut *slot } {
+      Slot::Occupied(_, value,) => Some(value,),
+      Slot::Vacant(_,) => None,
+    },)
+    .collect()
+  }
+  #[inline]
+  fn iter(&self,) -> Self::Iter<'_,> { CowStorage::iter(self,) }
+  #[inline]
+  fn iter_mut(&mut self,) -> Self::IterMut<'_,> {
+    CowIterMut { chunks: Arc::make_mut(&mut self.chunks,).iter_mut().enumerate(), slots: None, }
+  }
+  fn into_entries(self,) -> Self::IntoEntries {
+    let chunks = Arc::try_unwrap(self.chunks,).unwrap_or_else(|chunks,| (*chunks).clone(),);
+
+    chunks.into_iter().enumerate()
+    .flat_map(|(chunk, slots,),| {
+      let slots = Arc::try_unwrap(slots,).unwrap_or_else(|slots,| (*slots).clone(),);
+
+      SlotIntoIter::new(slots.into_iter(),).map(move |(index, generation, value,),| (chunk * CHUNK_LEN + index, generation, value,),)
+    },)
+    .collect::<Vec<_>>()
+    .into_iter()
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cow_storage() {
+    let mut storage = CowStorage::new();
+    let indices = (0..200).map(|value,| storage.insert(value as usize, value,).unwrap(),).collect::<Vec<_>>();
+    let snapshot = storage.clone();
+
+    assert!(storage.is_shared(indices[0],), "`CowStorage::clone` copied the chunks",);
+    *storage.get_mut(indices[0],).unwrap().1 = -1;
+    storage.remove(indices[150],);
+    assert!(!storage.is_shared(indices[0],), "`CowStorage::get_mut` did not copy the chunk",);
+    assert!(storage.is_shared(indices[100],), "`CowStorage::get_mut` copied every chunk",);
+    assert!(Arc::ptr_eq(&storage.chunks[1], &snapshot.chunks[1],), "`CowStorage::get_mut` copied every chunk",);
+
+    assert_eq!(snapshot.get(indices[0],), Some((0, &0,)), "`CowStorage::get_mut` changed a clone",);
+    assert_eq!(snapshot.get(indices[150],), Some((150, &150,)), "`CowStorage::remove` changed a clone",);
+    assert_eq!(storage.get(indices[0],), Some((0, &-1,)), "`CowStorage::get_mut` did not change the value",);
+    assert_eq!(storage.insert(200, 200,), Ok(indices[150]), "`CowStorage::insert` did not reuse the empty slot",);
+    assert_eq!(snapshot.iter().count(), 200, "`CowStorage::iter` skipped values",);
+
+    let shared = storage.clone();
+    let values = storage.get_disjoint_mut(&[indices[3], indices[70], indices[1],],).expect("`CowStorage::get_disjoint_mut` failed",);
+    for value in values { *value += 1000; }
+    assert_eq!([indices[3], indices[70], indices[1],].iter().map(|&index,| *storage.get(index,).unwrap().1,).collect::<Vec<_>>(), [1003, 1070, 1001], "`CowStorage::get_disjoint_mut` returned wrong values",);
+    assert_eq!(shared.get(indices[1],), Some((1, &1,)), "`CowStorage::get_disjoint_mut` changed a clone",);
+    assert_eq!(snapshot.into_entries().map(|(index, _, _,),| index,).collect::<Vec<_>>(), indices, "`CowStorage::into_entries` returned wrong indices",);
+  }
+}
@@ -0,0 +1,221 @@
+//! Defines the [PoolStorage] trait and the built in backing containers of a
+//! [TypePool](crate::TypePool).
+//! 
+//! # Example
+//! 
+//! ```
+//! use type_pool::{TypePool, storage::HashStorage,};
+//! 
+//! let mut pool = TypePool::<_, HashStorage<_>>::default();
+//! let key = pool.insert(10,);
+//! 
+//! assert_eq!(pool[key], 10);
+//! ```
+
+mod slab;
+mod hash;
+mod btree;
+mod array;
+mod cow;
+
+pub use self::{
+  slab::{SlabStorage, SlotIter, SlotIterMut, SlotIntoIter,},
+  hash::HashStorage,
+  btree::BTreeStorage,
+  array::ArrayStorage,
+  cow::{CowStorage, CowIter, CowIterMut,},
+};
+#[cfg(feature = "rayon",)]
+pub(crate) use self::slab::Slot;
+
+/// The storage used by a [TypePool](crate::TypePool) when none is specified.
+pub type DefaultStorage<T,> = SlabStorage<T,>;
+
+/// A container which stores the values of a [TypePool](crate::TypePool).
+/// 
+/// The storage chooses the index of each value while the pool chooses its generation;
+/// together they make up a [PoolKey](crate::PoolKey).
+pub trait PoolStorage<T,>: Default {
+  /// An iterator over the indices, generations and values of the storage.
+  type Iter<'a,>: Iterator<Item = (usize, usize, &'a T,)>
+    where Self: 'a, T: 'a;
+  /// An iterator over the indices, generations and mutable values of the storage.
+  type IterMut<'a,>: Iterator<Item = (usize, usize, &'a mut T,)>
+    where Self: 'a, T: 'a;
+  /// An iterator which moves the indices, generations and values out of the storage.
+  type IntoEntries: Iterator<Item = (usize, usize, T,)>;
+
+  /// Returns the number of values in the storage.
+  fn len(&self,) -> usize;
+  /// Returns `true` if the storage is empty.
+  #[inline]
+  fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Returns a new empty storage with room for at least `capacity` values.
+  fn with_capacity(capacity: usize,) -> Self {
+    let mut storage = Self::default();
+
+    storage.reserve(capacity,);
+    storage
+  }
+  /// Returns the number of values the storage can hold without reallocating.
+  #[inline]
+  fn capacity(&self,) -> usize { self.len() }
+  /// Reserves room for at least `additional` more values.
+  #[inline]
+  fn reserve(&mut self, additional: usize,) { let _ = additional; }
+  /// Reserves room for exactly `additional` more values where the storage allows it.
+  #[inline]
+  fn reserve_exact(&mut self, additional: usize,) { self.reserve(additional,) }
+  /// Releases as much unused memory as the storage allows.
+  #[inline]
+  fn shrink_to_fit(&mut self,) {}
+  /// Inserts `value` and returns its index or returns `value` if the storage is full.
+  /// 
+  /// # Params
+  /// 
+  /// generation --- The generation of `value`.  
+  /// value --- The value to insert.  
+  fn insert(&mut self, generation: usize, value: T,) -> Result<usize, T>;
+  /// Inserts `value` at the empty `index` or returns `value` if `index` is occupied or
+  /// cannot be filled.
+  /// 
+  /// Used to restore removed values under their original index. The default
+  /// implementation only fills `index` if it is the [PoolStorage::next_index], so storages
+  /// should override it to restore values removed out of order.
+  /// 
+  /// # Params
+  /// 
+  /// index --- The index to insert `value` at.  
+  /// generation --- The generation of `value`.  
+  /// value --- The value to insert.  
+  fn insert_at(&mut self, index: usize, generation: usize, value: T,) -> Result<(), T> {
+    if self.next_index() != Some(index,) { return Err(value) }
+
+    self.insert(generation, value,).map(|_,| (),)
+  }
+  /// Returns the index the next inserted value will be stored at or `None` if the storage
+  /// is full.
+  fn next_index(&self,) -> Option<usize>;
+  /// Removes the value at `index` and returns it with its generation.
+  fn remove(&mut self, index: usize,) -> Option<(usize, T,)>;
+  /// Returns the generation of the value at `index` and a reference to it.
+  fn get(&self, index: usize,) -> Option<(usize, &T,)>;
+  /// Returns the generation of the value at `index` and a mutable reference to it.
+  fn get_mut(&mut self, index: usize,) -> Option<(usize, &mut T,)>;
+  /// Returns unique references too the values at each of `indices` in the same order.
+  /// 
+  /// Returns `None` if any index is repeated or does not hold a value.
+  fn get_disjoint_mut(&mut self, indices: &[usize],) -> Option<Vec<&mut T>>;
+  /// Returns an iterator over the indices, generations and values of the storage.
+  fn iter(&self,) -> Self::Iter<'_,>;
+  /// Returns an iterator over the indices, generations and mutable values of the storage.
+  fn iter_mut(&mut self,) -> Self::IterMut<'_,>;
+  /// Returns an iterator which moves the indices, generations and values out of the
+  /// storage.
+  fn into_entries(self,) -> Self::IntoEntries;
+  /// Removes every value for which `keep` returns `false`.
+  /// 
+  /// # Params
+  /// 
+  /// keep --- Called with the index, generation and value of every value.  
+  fn retain<F,>(&mut self, mut keep: F,)
+    where F: FnMut(usize, usize, &mut T,) -> bool, {
+    let removed = self.iter_mut()
+      .filter_map(|(index, generation, value,),| if keep(index, generation, value,) { None } else { Some(index,) },)
+      .collect::<Vec<_>>();
+
+    for index in removed { self.remove(index,); }
+  }
+}
+
+/// Returns `true` if no index in `indices` is repeated.
+/// 
+/// The indices are compared pairwise so that checking does not allocate; callers pass a
+/// handful of indices.
+fn all_distinct(indices: &[usize],) -> bool {
+  indices.iter().enumerate().all(|(at, index,),| !indices[..at].contains(index,),)
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{TypePool, PoolError,};
+
+  /// The tests every [PoolStorage] must pass.
+  fn conformance<S,>()
+    where S: PoolStorage<i32,>, {
+    let mut storage = S::default();
+    let a = storage.insert(0, 1,).expect("`PoolStorage::insert` failed",);
+    let b = storage.insert(1, 2,).expect("`PoolStorage::insert` failed",);
+    let c = storage.insert(2, 3,).expect("`PoolStorage::insert` failed",);
+
+    assert_eq!(storage.len(), 3, "`PoolStorage::len` is wrong",);
+    assert_eq!(storage.get(b,), Some((1, &2,)), "`PoolStorage::get` returned wrong value",);
+    *storage.get_mut(c,).expect("`PoolStorage::get_mut` failed",).1 += 10;
+    assert_eq!(storage.remove(b,), Some((1, 2,)), "`PoolStorage::remove` returned wrong value",);
+    assert_eq!(storage.remove(b,), None, "`PoolStorage::remove` removed a missing value",);
+    assert!(storage.get(b,).is_none(), "`PoolStorage::get` returned a removed value",);
+
+    storage.reserve(10,);
+    assert!(storage.capacity() >= storage.len(), "`PoolStorage::capacity` is less than the length",);
+    storage.shrink_to_fit();
+    assert_eq!(storage.get(a,), Some((0, &1,)), "`PoolStorage::shrink_to_fit` lost a value",);
+
+    let next = storage.next_index();
+    let d = storage.insert(3, 4,).expect("`PoolStorage::insert` failed",);
+    assert_eq!(next, Some(d,), "`PoolStorage::next_index` returned wrong index",);
+    assert!(d != a && d != c, "`PoolStorage::insert` reused an occupied index",);
+
+    let e = storage.insert(4, 5,).expect("`PoolStorage::insert` failed",);
+    let f = storage.insert(5, 6,).expect("`PoolStorage::insert` failed",);
+    storage.remove(e,);
+    storage.remove(f,);
+    assert_eq!(storage.insert_at(e, 4, 5,), Ok(()), "`PoolStorage::insert_at` failed",);
+    assert_eq!(storage.insert_at(e, 4, 5,), Err(5), "`PoolStorage::insert_at` filled an occupied index",);
+    assert!(storage.get(f,).is_none(), "`PoolStorage::insert_at` filled the wrong index",);
+    assert_eq!(storage.remove(e,), Some((4, 5,)), "`PoolStorage::insert_at` inserted at the wrong index",);
+
+    let values = storage.get_disjoint_mut(&[c, a, d,],).expect("`PoolStorage::get_disjoint_mut` failed",);
+    assert_eq!(values.into_iter().map(|v,| *v,).collect::<Vec<_>>(), [13, 1, 4], "`PoolStorage::get_disjoint_mut` returned wrong order",);
+    assert!(storage.get_disjoint_mut(&[a, a,],).is_none(), "`PoolStorage::get_disjoint_mut` accepted duplicates",);
+    assert!(storage.get_disjoint_mut(&[a, usize::MAX,],).is_none(), "`PoolStorage::get_disjoint_mut` accepted a missing index",);
+
+    let mut pool = TypePool::<i32, S,>::default();
+    let key1 = pool.insert(1,);
+    let key2 = pool.insert(2,);
+
+    pool.remove(key1,);
+    assert_eq!(pool.get(key1,), Err(PoolError::StaleKey), "`TypePool::get` accepted a stale key",);
+    assert_eq!(pool.get(key2,), Ok(&2), "`TypePool::get` returned wrong value",);
+
+    let mut entries = storage.iter().map(|(index, generation, value,),| (index, generation, *value,),).collect::<Vec<_>>();
+    entries.sort_unstable();
+    let mut expected = vec![(a, 0, 1,), (c, 2, 13,), (d, 3, 4,)];
+    expected.sort_unstable();
+    assert_eq!(entries, expected, "`PoolStorage::iter` returned wrong entries",);
+
+    for (_, _, value,) in storage.iter_mut() { *value *= 2; }
+    storage.retain(|index, _, _,| index != c,);
+    let mut entries = storage.into_entries().collect::<Vec<_>>();
+    entries.sort_unstable();
+    let mut expected = vec![(a, 0, 2,), (d, 3, 8,)];
+    expected.sort_unstable();
+    assert_eq!(entries, expected, "`PoolStorage::retain` kept wrong entries",);
+  }
+  #[test]
+  fn test_slab_storage() { conformance::<SlabStorage<_,>>() }
+  #[test]
+  fn test_hash_storage() { conformance::<HashStorage<_,>>() }
+  #[test]
+  fn test_btree_storage() { conformance::<BTreeStorage<_,>>() }
+  #[test]
+  fn test_cow_storage() { conformance::<CowStorage<_,>>() }
+  #[test]
+  fn test_array_storage() {
+    conformance::<ArrayStorage<_, 8,>>();
+
+    let mut pool = TypePool::<_, ArrayStorage<_, 1,>>::default();
+    pool.insert(1,);
+    assert_eq!(pool.try_insert(2,).err(), Some((PoolError::CapacityExhausted, 2,)), "`ArrayStorage` exceeded its capacity",);
+  }
+}
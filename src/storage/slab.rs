@@ -0,0 +1,334 @@
+//! Defines the contiguous slot storage backing a [TypePool](crate::TypePool) by default.
+
+use super::{PoolStorage, all_distinct,};
+use std::{mem, slice, iter::Enumerate,};
+
+/// A slot in a [SlabStorage], [ArrayStorage](super::ArrayStorage) or
+/// [CowStorage](super::CowStorage).
+#[derive(Clone,)]
+pub enum Slot<T,> {
+  /// A slot holding a value and the generation it was inserted with.
+  Occupied(usize, T,),
+  /// An empty slot and the index of the next empty slot in the free list.
+  Vacant(Option<usize>,),
+}
+
+/// Moves `value` into the first empty slot of the free list and returns its index.
+/// 
+/// # Panics
+/// 
+/// If the free list is empty.
+pub(super) fn fill<T,>(slots: &mut [Slot<T,>], free: &mut Option<usize>, generation: usize, value: T,) -> usize {
+  let index = free.expect("`slab::fill` no empty slots",);
+
+  match mem::replace(&mut slots[index], Slot::Occupied(generation, value,),) {
+    Slot::Vacant(next,) => *free = next,
+    Slot::Occupied(..) => unreachable!("`slab::fill` free list contains an occupied slot",),
+  }
+
+  index
+}
+
+/// Moves `value` into the empty slot at `index` and removes the slot from the free list.
+/// 
+/// Returns `value` if the slot at `index` does not exist or is occupied.
+pub(super) fn fill_at<T,>(slots: &mut [Slot<T,>], free: &mut Option<usize>, index: usize, generation: usize, value: T,) -> Result<(), T> {
+  let next = match slots.get(index,) {
+    Some(Slot::Vacant(next,),) => *next,
+    _ => return Err(value),
+  };
+
+  //The slot is usually the head of the free list when values are restored in reverse.
+  if *free == Some(index,) { *free = next }
+  else {
+    let mut prev = free.expect("`slab::fill_at` free list is corrupted",);
+
+    loop {
+      match slots[prev] {
+        Slot::Vacant(Some(link,),) if link == index => break,
+        Slot::Vacant(Some(link,),) => prev = link,
+        _ => unreachable!("`slab::fill_at` free list is corrupted",),
+      }
+    }
+    slots[prev] = Slot::Vacant(next,);
+  }
+
+  slots[index] = Slot::Occupied(generation, value,);
+  Ok(())
+}
+
+/// Moves the value out of the slot at `index` and adds the slot to the free list.
+pub(super) fn vacate<T,>(slots: &mut [Slot<T,>], free: &mut Option<usize>, index: usize,) -> Option<(usize, T,)> {
+  match slots.get_mut(index,) {
+    Some(slot @ Slot::Occupied(..),) => match mem::replace(slot, Slot::Vacant(*free,),) {
+      Slot::Occupied(generation, value,) => {
+        *free = Some(index,);
+        Some((generation, value,))
+      },
+      Slot::Vacant(_,) => unreachable!(),
+    },
+    _ => None,
+  }
+}
+
+/// Returns the generation of the value at `index` and a reference to it.
+#[inline]
+pub(super) fn get<T,>(slots: &[Slot<T,>], index: usize,) -> Option<(usize, &T,)> {
+  match slots.get(index,) {
+    Some(Slot::Occupied(generation, value,),) => Some((*generation, value,)),
+    _ => None,
+  }
+}
+
+/// Returns the generation of the value at `index` and a mutable reference to it.
+#[inline]
+pub(super) fn get_mut<T,>(slots: &mut [Slot<T,>], index: usize,) -> Option<(usize, &mut T,)> {
+  match slots.get_mut(index,) {
+    Some(Slot::Occupied(generation, value,),) => Some((*generation, value,)),
+    _ => None,
+  }
+}
+
+/// Returns unique references too the values at each of `indices` in the same order.
+pub(super) fn get_disjoint_mut<'a, T,>(slots: &'a mut [Slot<T,>], indices: &[usize],) -> Option<Vec<&'a mut T>> {
+  if !all_distinct(indices,) { return None }
+
+  let len = slots.len();
+  //`as_mut_ptr` does not borrow the slots so each slot is only borrowed once below.
+  let slots = slots.as_mut_ptr();
+
+  indices.iter()
+  .map(|&index,| {
+    if index >= len { return None }
+
+    //The indices are distinct and in bounds so the references do not alias.
+    match unsafe { &mut *slots.add(index,) } {
+      Slot::Occupied(_, value,) => Some(value,),
+      Slot::Vacant(_,) => None,
+    }
+  },)
+  .collect()
+}
+
+/// An iterator over the occupied slots of a [SlabStorage] or
+/// [ArrayStorage](super::ArrayStorage).
+pub struct SlotIter<'a, T,>(Enumerate<slice::Iter<'a, Slot<T,>>>,);
+
+impl<'a, T,> SlotIter<'a, T,> {
+  /// Returns an iterator over the occupied slots of `slots`.
+  #[inline]
+  pub(super) fn new(slots: &'a [Slot<T,>],) -> Self { SlotIter(slots.iter().enumerate(),) }
+}
+
+impl<'a, T,> Iterator for SlotIter<'a, T,> {
+  type Item = (usize, usize, &'a T,);
+
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.0.find_map(|(index, slot,),| match slot {
+      Slot::Occupied(generation, value,) => Some((index, *generation, value,)),
+      Slot::Vacant(_,) => None,
+    },)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { (0, self.0.size_hint().1,) }
+}
+
+/// An iterator over the occupied slots of a [SlabStorage] or
+/// [ArrayStorage](super::ArrayStorage) which yields mutable values.
+pub struct SlotIterMut<'a, T,>(Enumerate<slice::IterMut<'a, Slot<T,>>>,);
+
+impl<'a, T,> SlotIterMut<'a, T,> {
+  /// Returns an iterator over the occupied slots of `slots`.
+  #[inline]
+  pub(super) fn new(slots: &'a mut [Slot<T,>],) -> Self { SlotIterMut(slots.iter_mut().enumerate(),) }
+}
+
+impl<'a, T,> Iterator for SlotIterMut<'a, T,> {
+  type Item = (usize, usize, &'a mut T,);
+
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.0.find_map(|(index, slot,),| match slot {
+      Slot::Occupied(generation, value,) => Some((index, *generation, value,)),
+      Slot::Vacant(_,) => None,
+    },)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { (0, self.0.size_hint().1,) }
+}
+
+/// An iterator which moves the values out of the occupied slots of a [SlabStorage] or
+/// [ArrayStorage](super::ArrayStorage).
+pub struct SlotIntoIter<I,>(Enumerate<I,>,);
+
+impl<I,> SlotIntoIter<I,> {
+  /// Returns an iterator over the occupied slots of `slots`.
+  #[inline]
+  pub(super) fn new<T,>(slots: I,) -> Self
+    where I: Iterator<Item = Slot<T,>>, {
+    SlotIntoIter(slots.enumerate(),)
+  }
+}
+
+impl<T, I,> Iterator for SlotIntoIter<I,>
+  where I: Iterator<Item = Slot<T,>>, {
+  type Item = (usize, usize, T,);
+
+  fn next(&mut self,) -> Option<Self::Item> {
+    self.0.find_map(|(index, slot,),| match slot {
+      Slot::Occupied(generation, value,) => Some((index, generation, value,)),
+      Slot::Vacant(_,) => None,
+    },)
+  }
+  #[inline]
+  fn size_hint(&self,) -> (usize, Option<usize>,) { (0, self.0.size_hint().1,) }
+}
+
+/// A contiguous list of slots with an intrusive free list of the empty slots.
+/// 
+/// Inserting, removing and accessing values are all `O(1)`.
+pub struct SlabStorage<T,> {
+  /// The slots of the storage.
+  slots: Vec<Slot<T,>>,
+  /// The index of the first empty slot.
+  free: Option<usize>,
+  /// The number of occupied slots.
+  len: usize,
+}
+
+impl<T,> SlabStorage<T,> {
+  /// Returns a new empty SlabStorage.
+  #[inline]
+  pub const fn new() -> Self {
+    Self { slots: Vec::new(), free: None, len: 0, }
+  }
+  /// Returns the slots of this SlabStorage.
+  #[cfg(feature = "rayon",)]
+  #[inline]
+  pub(crate) fn slots(&self,) -> &[Slot<T,>] { &self.slots }
+  /// Returns the mutable slots of this SlabStorage.
+  #[cfg(feature = "rayon",)]
+  #[inline]
+  pub(crate) fn slots_mut(&mut self,) -> &mut [Slot<T,>] { &mut self.slots }
+}
+
+impl<T,> Default for SlabStorage<T,> {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl<T,> PoolStorage<T,> for SlabStorage<T,> {
+  type Iter<'a,> = SlotIter<'a, T,>
+    where T: 'a;
+  type IterMut<'a,> = SlotIterMut<'a, T,>
+    where T: 'a;
+  type IntoEntries = SlotIntoIter<std::vec::IntoIter<Slot<T,>>>;
+
+  #[inline]
+  fn len(&self,) -> usize { self.len }
+  #[inline]
+  fn with_capacity(capacity: usize,) -> Self {
+    Self { slots: Vec::with_capacity(capacity,), free: None, len: 0, }
+  }
+  #[inline]
+  fn capacity(&self,) -> usize { self.slots.capacity() }
+  #[inline]
+  fn reserve(&mut self, additional: usize,) {
+    //Empty slots are reused before the `Vec` grows.
+    self.slots.reserve(additional.saturating_sub(self.slots.len() - self.len,),)
+  }
+  #[inline]
+  fn reserve_exact(&mut self, additional: usize,) {
+    self.slots.reserve_exact(additional.saturating_sub(self.slots.len() - self.len,),)
+  }
+  fn shrink_to_fit(&mut self,) {
+    let len = self.slots.len();
+
+    while let Some(Slot::Vacant(_,),) = self.slots.last() { self.slots.pop(); }
+    //Rebuild the free list without the truncated slots.
+    if self.slots.len() != len {
+      self.free = None;
+      for (index, slot,) in self.slots.iter_mut().enumerate().rev() {
+        if let Slot::Vacant(next,) = slot {
+          *next = self.free;
+          self.free = Some(index,);
+        }
+      }
+    }
+
+    self.slots.shrink_to_fit()
+  }
+  fn insert(&mut self, generation: usize, value: T,) -> Result<usize, T> {
+    if self.len == usize::MAX { return Err(value) }
+
+    self.len += 1;
+    if self.free.is_some() { return Ok(fill(&mut self.slots, &mut self.free, generation, value,)) }
+
+    self.slots.push(Slot::Occupied(generation, value,),);
+    Ok(self.slots.len() - 1)
+  }
+  fn insert_at(&mut self, index: usize, generation: usize, value: T,) -> Result<(), T> {
+    //Slots truncated by `shrink_to_fit` are added back to the free list.
+    while self.slots.len() <= index {
+      self.slots.push(Slot::Vacant(self.free,),);
+      self.free = Some(self.slots.len() - 1,);
+    }
+
+    fill_at(&mut self.slots, &mut self.free, index, generation, value,)?;
+    self.len += 1;
+    Ok(())
+  }
+  #[inline]
+  fn next_index(&self,) -> Option<usize> {
+    if self.len == usize::MAX { None }
+    else { Some(self.free.unwrap_or(self.slots.len(),),) }
+  }
+  fn remove(&mut self, index: usize,) -> Option<(usize, T,)> {
+    let value = vacate(&mut self.slots, &mut self.free, index,)?;
+
+    self.len -= 1;
+    Some(value,)
+  }
+  #[inline]
+  fn get(&self, index: usize,) -> Option<(usize, &T,)> { get(&self.slots, index,) }
+  #[inline]
+  fn get_mut(&mut self, index: usize,) -> Option<(usize, &mut T,)> { get_mut(&mut self.slots, index,) }
+  #[inline]
+  fn get_disjoint_mut(&mut self, indices: &[usize],) -> Option<Vec<&mut T>> {
+    get_disjoint_mut(&mut self.slots, indices,)
+  }
+  #[inline]
+  fn iter(&self,) -> Self::Iter<'_,> { SlotIter::new(&self.slots,) }
+  #[inline]
+  fn iter_mut(&mut self,) -> Self::IterMut<'_,> { SlotIterMut::new(&mut self.slots,) }
+  #[inline]
+  fn into_entries(self,) -> Self::IntoEntries { SlotIntoIter::new(self.slots.into_iter(),) }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_slab() {
+    let mut slab = SlabStorage::new();
+    let a = slab.insert(0, 'a',).unwrap();
+    let b = slab.insert(1, 'b',).unwrap();
+    let c = slab.insert(2, 'c',).unwrap();
+
+    assert_eq!(slab.remove(b,), Some((1, 'b',)), "`SlabStorage::remove` returned wrong value",);
+    assert_eq!(slab.remove(b,), None, "`SlabStorage::remove` removed an empty slot",);
+    assert_eq!(slab.insert(3, 'd',), Ok(b), "`SlabStorage::insert` did not reuse the empty slot",);
+    assert_eq!(slab.insert(4, 'e',), Ok(3), "`SlabStorage::insert` did not append",);
+    assert_eq!(slab.len(), 4, "`SlabStorage::len` is wrong",);
+    assert_eq!(slab.get(a,), Some((0, &'a',)), "`SlabStorage::get` returned wrong value",);
+    assert_eq!(slab.get_mut(c,).map(|(g, v,),| (g, *v,),), Some((2, 'c',)), "`SlabStorage::get_mut` returned wrong value",);
+
+    slab.remove(a,);
+    slab.remove(3,);
+    slab.shrink_to_fit();
+    assert_eq!(slab.slots.len(), 3, "`SlabStorage::shrink_to_fit` kept the trailing slots",);
+    assert_eq!(slab.next_index(), Some(a,), "`SlabStorage::shrink_to_fit` corrupted the free list",);
+    assert_eq!(slab.insert(5, 'f',), Ok(a), "`SlabStorage::insert` did not reuse the empty slot",);
+    assert_eq!(slab.insert(6, 'g',), Ok(3), "`SlabStorage::insert` did not append",);
+  }
+}
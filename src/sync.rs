@@ -0,0 +1,288 @@
+//! Defines the [SyncTypePool] which can be shared between threads.
+
+use crate::{
+  PoolKey, PoolError, KeyState, next_pool_id,
+  storage::{PoolStorage, DefaultStorage,},
+};
+use std::{
+  ops,
+  num::NonZeroUsize,
+  marker::PhantomData,
+  sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, PoisonError, atomic::{AtomicUsize, Ordering,},},
+};
+
+/// A pool of `T` values which can be modified through a shared reference.
+/// 
+/// The values are split between several independently locked shards so threads only
+/// contend when they access values in the same shard. Each shard is a [PoolStorage] `S`.
+/// 
+/// Lock poisoning is ignored: after a panic while a value is locked its whole shard stays
+/// usable, including that value, which may have been left partly modified.
+/// 
+/// # Example
+/// 
+/// ```
+/// use type_pool::SyncTypePool;
+/// use std::thread;
+/// 
+/// let pool = SyncTypePool::new();
+/// let key = pool.insert(0,);
+/// 
+/// thread::scope(|scope,| for _ in 0..4 {
+///   scope.spawn(|| *pool.write(key,).unwrap() += 1,);
+/// },);
+/// assert_eq!(*pool.read(key,).unwrap(), 4);
+/// ```
+pub struct SyncTypePool<T, S = DefaultStorage<T,>,> {
+  shards: Box<[RwLock<S,>]>,
+  next_shard: AtomicUsize,
+  next_generation: AtomicUsize,
+  pool_id: NonZeroUsize,
+  _values: PhantomData<T,>,
+}
+
+impl<T,> SyncTypePool<T,> {
+  /// Returns a new empty SyncTypePool with a shard count suited to this machine.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+  /// Returns a new empty SyncTypePool with `shards` shards.
+  /// 
+  /// # Panics
+  /// 
+  /// If `shards` is `0`.
+  #[inline]
+  pub fn with_shards(shards: usize,) -> Self { Self::with_shards_in(shards,) }
+}
+
+impl<T, S,> SyncTypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Returns a new empty SyncTypePool with `shards` shards.
+  /// 
+  /// # Panics
+  /// 
+  /// If `shards` is `0`.
+  pub fn with_shards_in(shards: usize,) -> Self {
+    assert_ne!(shards, 0, "`SyncTypePool` must have at least one shard",);
+
+    Self {
+      shards: (0..shards).map(|_,| RwLock::new(S::default(),),).collect(),
+      next_shard: AtomicUsize::new(0,),
+      next_generation: AtomicUsize::new(0,),
+      pool_id: next_pool_id(),
+      _values: PhantomData,
+    }
+  }
+  /// Returns the shard holding `key` and the index of `key` in that shard.
+  #[inline]
+  fn locate(&self, key: &PoolKey<T,>,) -> (&RwLock<S,>, usize,) {
+    (&self.shards[key.0 % self.shards.len()], key.0 / self.shards.len(),)
+  }
+  /// Read locks `shard`.
+  #[inline]
+  fn read_shard(shard: &RwLock<S,>,) -> RwLockReadGuard<'_, S,> {
+    shard.read().unwrap_or_else(PoisonError::into_inner,)
+  }
+  /// Write locks `shard`.
+  #[inline]
+  fn write_shard(shard: &RwLock<S,>,) -> RwLockWriteGuard<'_, S,> {
+    shard.write().unwrap_or_else(PoisonError::into_inner,)
+  }
+  /// Returns the [KeyState] of `key` in a locked shard.
+  fn shard_state(&self, shard: &S, index: usize, key: &PoolKey<T,>,) -> KeyState {
+    if !self.owns_key(key,) || key.2 >= self.next_generation.load(Ordering::Acquire,) {
+      return KeyState::Unknown
+    }
+
+    match shard.get(index,) {
+      Some((generation, _,),) if generation == key.2 => KeyState::Live,
+      _ => KeyState::Removed,
+    }
+  }
+  /// Returns `Ok` if `key` refers to a value in a locked shard.
+  fn check_key(&self, shard: &S, index: usize, key: &PoolKey<T,>,) -> Result<(), PoolError> {
+    match self.shard_state(shard, index, key,) {
+      KeyState::Live => Ok(()),
+      KeyState::Removed => Err(PoolError::StaleKey),
+      KeyState::Unknown if self.owns_key(key,) => Err(PoolError::MissingKey),
+      KeyState::Unknown => Err(PoolError::ForeignKey),
+    }
+  }
+  /// Returns `true` if `key` was issued by this SyncTypePool.
+  #[inline]
+  pub fn owns_key(&self, key: &PoolKey<T,>,) -> bool { key.1 == self.pool_id }
+  /// Returns the [KeyState] of `key` in this SyncTypePool.
+  pub fn key_state(&self, key: &PoolKey<T,>,) -> KeyState {
+    let (shard, index,) = self.locate(key,);
+
+    self.shard_state(&Self::read_shard(shard,), index, key,)
+  }
+  /// Returns `true` if this SyncTypePool contains `key`.
+  #[inline]
+  pub fn contains_key(&self, key: &PoolKey<T,>,) -> bool {
+    self.key_state(key,) == KeyState::Live
+  }
+  /// Returns the number of values in this SyncTypePool.
+  /// 
+  /// Other threads may change the number of values while they are counted.
+  pub fn len(&self,) -> usize {
+    self.shards.iter().map(|shard,| Self::read_shard(shard,).len(),).sum()
+  }
+  /// Returns `true` if this SyncTypePool is empty.
+  #[inline]
+  pub fn is_empty(&self,) -> bool { self.len() == 0 }
+  /// Inserts `value` into the SyncTypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the SyncTypePool is full.
+  pub fn insert(&self, value: T,) -> PoolKey<T,> {
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("{}", e),)
+  }
+  /// Attempts to insert `value` into the SyncTypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the SyncTypePool is full.
+  pub fn try_insert(&self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)> {
+    let shard_no = self.next_shard.fetch_add(1, Ordering::Relaxed,) % self.shards.len();
+    let mut shard = Self::write_shard(&self.shards[shard_no],);
+    //The generation is taken before inserting because the slot stores it; a generation
+    //skipped by a failed insert is never handed out, which keeps every key unique.
+    let generation = match self.next_generation.fetch_update(Ordering::AcqRel, Ordering::Acquire, |g,| g.checked_add(1,),) {
+      Ok(generation,) => generation,
+      Err(_,) => return Err((PoolError::CapacityExhausted, value,)),
+    };
+    let index = shard.insert(generation, value,).map_err(|value,| (PoolError::CapacityExhausted, value,),)?;
+    let id = index.checked_mul(self.shards.len(),).and_then(|id,| id.checked_add(shard_no,),);
+
+    match id {
+      Some(id,) => Ok(PoolKey(id, self.pool_id, generation, PhantomData,)),
+      None => Err((PoolError::CapacityExhausted, shard.remove(index,).unwrap().1,)),
+    }
+  }
+  /// Removes the value mapped too `key`.
+  /// 
+  /// Returns `None` if the value has already been removed.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is not owned by this pool.
+  pub fn remove(&self, key: PoolKey<T,>,) -> Option<T> {
+    assert!(self.owns_key(&key,), "`SyncTypePool::remove` `key` must be owned by this pool",);
+
+    self.try_remove(key,).ok()
+  }
+  /// Attempts to remove the value mapped too `key`.
+  pub fn try_remove(&self, key: PoolKey<T,>,) -> Result<T, PoolError> {
+    let (shard, index,) = self.locate(&key,);
+    let mut shard = Self::write_shard(shard,);
+
+    self.check_key(&shard, index, &key,)?;
+    Ok(shard.remove(index,).unwrap().1)
+  }
+  /// Locks the value mapped too `key` for reading.
+  /// 
+  /// Blocks while another thread is writing to the shard holding `key`.
+  pub fn read(&self, key: PoolKey<T,>,) -> Result<ReadGuard<'_, T, S,>, PoolError> {
+    let (shard, index,) = self.locate(&key,);
+    let guard = Self::read_shard(shard,);
+
+    self.check_key(&guard, index, &key,)?;
+    Ok(ReadGuard { guard, index, _values: PhantomData, })
+  }
+  /// Locks the value mapped too `key` for writing.
+  /// 
+  /// Blocks while another thread is accessing the shard holding `key`.
+  pub fn write(&self, key: PoolKey<T,>,) -> Result<WriteGuard<'_, T, S,>, PoolError> {
+    let (shard, index,) = self.locate(&key,);
+    let guard = Self::write_shard(shard,);
+
+    self.check_key(&guard, index, &key,)?;
+    Ok(WriteGuard { guard, index, _values: PhantomData, })
+  }
+}
+
+impl<T, S,> Default for SyncTypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  fn default() -> Self {
+    let shards = std::thread::available_parallelism().map_or(1, NonZeroUsize::get,) * 4;
+
+    Self::with_shards_in(shards.next_power_of_two(),)
+  }
+}
+
+/// A shared lock on a value in a [SyncTypePool].
+pub struct ReadGuard<'a, T, S,> {
+  guard: RwLockReadGuard<'a, S,>,
+  index: usize,
+  _values: PhantomData<&'a T,>,
+}
+
+impl<T, S,> ops::Deref for ReadGuard<'_, T, S,>
+  where S: PoolStorage<T,>, {
+  type Target = T;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { self.guard.get(self.index,).unwrap().1 }
+}
+
+/// An exclusive lock on a value in a [SyncTypePool].
+pub struct WriteGuard<'a, T, S,> {
+  guard: RwLockWriteGuard<'a, S,>,
+  index: usize,
+  _values: PhantomData<&'a mut T,>,
+}
+
+impl<T, S,> ops::Deref for WriteGuard<'_, T, S,>
+  where S: PoolStorage<T,>, {
+  type Target = T;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { self.guard.get(self.index,).unwrap().1 }
+}
+
+impl<T, S,> ops::DerefMut for WriteGuard<'_, T, S,>
+  where S: PoolStorage<T,>, {
+  #[inline]
+  fn deref_mut(&mut self,) -> &mut Self::Target { self.guard.get_mut(self.index,).unwrap().1 }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use std::thread;
+
+  #[test]
+  fn test_sync_type_pool() {
+    let pool = SyncTypePool::with_shards(3,);
+    let keys = thread::scope(|scope,| {
+      let handles = (0..4).map(|t,| {
+        let pool = &pool;
+
+        scope.spawn(move || (0..50).map(|i,| pool.insert(t * 100 + i,),).collect::<Vec<_>>(),)
+      },).collect::<Vec<_>>();
+
+      handles.into_iter().flat_map(|handle,| handle.join().unwrap(),).collect::<Vec<_>>()
+    },);
+
+    assert_eq!(pool.len(), 200, "`SyncTypePool::insert` lost values",);
+    let originals = keys.iter().map(|&key,| *pool.read(key,).unwrap(),).collect::<Vec<_>>();
+    thread::scope(|scope,| for chunk in keys.chunks(50,) {
+      let pool = &pool;
+
+      scope.spawn(move || for &key in chunk { *pool.write(key,).unwrap() += 1; },);
+    },);
+    for (&key, original,) in keys.iter().zip(originals,) {
+      assert_eq!(*pool.read(key,).unwrap(), original + 1, "`SyncTypePool::write` lost or repeated a write",);
+    }
+
+    let value = *pool.read(keys[7],).unwrap();
+    assert_eq!(pool.remove(keys[7],), Some(value,), "`SyncTypePool::remove` returned wrong value",);
+    assert_eq!(pool.read(keys[7],).err(), Some(PoolError::StaleKey), "`SyncTypePool::read` accepted a stale key",);
+    assert_eq!(pool.try_remove(keys[7],), Err(PoolError::StaleKey), "`SyncTypePool::try_remove` removed a stale key",);
+
+    let key = pool.insert(1000,);
+    assert_ne!(key, keys[7], "`SyncTypePool::insert` reissued a removed key",);
+    assert_eq!(SyncTypePool::<i32,>::new().read(key,).err(), Some(PoolError::ForeignKey), "`SyncTypePool::read` accepted a foreign key",);
+  }
+}
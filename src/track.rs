@@ -0,0 +1,287 @@
+//! Defines the opt in change tracking and observers of a [TypePool].
+
+use crate::{TypePool, PoolKey, storage::PoolStorage,};
+use std::{fmt, mem, marker::PhantomData, collections::HashSet,};
+
+/// The keys changed in a [TypePool] since changes were last taken.
+/// 
+/// A key is only recorded once: a value inserted and then modified is only recorded as
+/// inserted and a value inserted and then removed is not recorded at all. Likewise a value
+/// removed and then restored by a rolled back [Transaction](crate::Transaction) or an undo
+/// is not recorded as removed.
+pub struct Changes<T,> {
+  inserted: HashSet<PoolKey<T,>>,
+  removed: HashSet<PoolKey<T,>>,
+  modified: HashSet<PoolKey<T,>>,
+  /// The removed keys which were recorded as modified before they were removed.
+  removed_modified: HashSet<PoolKey<T,>>,
+}
+
+impl<T,> Changes<T,> {
+  /// Returns the keys of the inserted values.
+  #[inline]
+  pub fn inserted(&self,) -> &HashSet<PoolKey<T,>> { &self.inserted }
+  /// Returns the keys of the removed values.
+  #[inline]
+  pub fn removed(&self,) -> &HashSet<PoolKey<T,>> { &self.removed }
+  /// Returns the keys of the values which may have been modified.
+  #[inline]
+  pub fn modified(&self,) -> &HashSet<PoolKey<T,>> { &self.modified }
+  /// Returns `true` if no changes were recorded.
+  #[inline]
+  pub fn is_empty(&self,) -> bool {
+    self.inserted.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+  }
+}
+
+impl<T,> Default for Changes<T,> {
+  #[inline]
+  fn default() -> Self {
+    Self {
+      inserted: HashSet::new(),
+      removed: HashSet::new(),
+      modified: HashSet::new(),
+      removed_modified: HashSet::new(),
+    }
+  }
+}
+
+impl<T,> fmt::Debug for Changes<T,> {
+  fn fmt(&self, fmt: &mut fmt::Formatter,) -> fmt::Result {
+    fmt.debug_struct("Changes",)
+    .field("inserted", &self.inserted,)
+    .field("removed", &self.removed,)
+    .field("modified", &self.modified,)
+    .finish()
+  }
+}
+
+/// The identity of an observer registered with a [TypePool].
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug,)]
+pub struct ObserverId(usize,);
+
+/// An observer of the values inserted into or removed from a [TypePool].
+type Observer<T,> = Box<dyn FnMut(PoolKey<T,>, &T,) + Send + Sync>;
+
+/// The change tracking state of a [TypePool].
+pub(crate) struct Tracker<T,> {
+  changes: Option<Changes<T,>>,
+  on_insert: Vec<(ObserverId, Observer<T,>,)>,
+  on_remove: Vec<(ObserverId, Observer<T,>,)>,
+  next_observer: usize,
+}
+
+impl<T,> Tracker<T,> {
+  /// Returns a new Tracker which tracks nothing.
+  #[inline]
+  pub(crate) const fn new() -> Self {
+    Self { changes: None, on_insert: Vec::new(), on_remove: Vec::new(), next_observer: 0, }
+  }
+  /// Returns `true` if changes are recorded or observed.
+  #[inline]
+  pub(crate) fn is_active(&self,) -> bool {
+    self.changes.is_some() || !self.on_insert.is_empty() || !self.on_remove.is_empty()
+  }
+  /// Records that `value` was inserted under `key`.
+  pub(crate) fn inserted(&mut self, key: PoolKey<T,>, value: &T,) {
+    if let Some(changes,) = &mut self.changes { changes.inserted.insert(key,); }
+    for (_, observer,) in self.on_insert.iter_mut() { observer(key, value,) }
+  }
+  /// Records that `value` was removed from under `key`.
+  pub(crate) fn removed(&mut self, key: PoolKey<T,>, value: &T,) {
+    if let Some(changes,) = &mut self.changes {
+      let modified = changes.modified.remove(&key,);
+
+      //A value which was never seen outside of the changes is forgotten.
+      if !changes.inserted.remove(&key,) {
+        changes.removed.insert(key,);
+        if modified { changes.removed_modified.insert(key,); }
+      }
+    }
+    for (_, observer,) in self.on_remove.iter_mut() { observer(key, value,) }
+  }
+  /// Records that the removed `value` was put back under `key`.
+  /// 
+  /// Observers already saw the value removed so they see it inserted again.
+  pub(crate) fn restored(&mut self, key: PoolKey<T,>, value: &T,) {
+    if let Some(changes,) = &mut self.changes {
+      //A removal which was not taken yet is cancelled rather than recorded as an insert.
+      if changes.removed.remove(&key,) {
+        if changes.removed_modified.remove(&key,) { changes.modified.insert(key,); }
+      } else { changes.inserted.insert(key,); }
+    }
+    for (_, observer,) in self.on_insert.iter_mut() { observer(key, value,) }
+  }
+  /// Records that the value of `key` may have been modified.
+  #[inline]
+  pub(crate) fn modified(&mut self, key: PoolKey<T,>,) {
+    if let Some(changes,) = &mut self.changes {
+      if !changes.inserted.contains(&key,) { changes.modified.insert(key,); }
+    }
+  }
+  /// Returns the next observer identity.
+  #[inline]
+  fn next_observer(&mut self,) -> ObserverId {
+    self.next_observer += 1;
+    ObserverId(self.next_observer - 1,)
+  }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Starts or stops recording the keys of changed values.
+  /// 
+  /// Values are recorded as modified whenever a mutable reference to them is handed
+  /// out, by [TypePool::get_mut], `IndexMut`, the disjoint access methods or the mutable
+  /// iterators, whether or not the value is actually changed. The values passed to the
+  /// `keep` function of [TypePool::retain] are not recorded as modified.
+  /// 
+  /// Stopping discards the recorded changes.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let mut pool = TypePool::new();
+  /// let key1 = pool.insert(1,);
+  /// 
+  /// pool.track_changes(true,);
+  /// let key2 = pool.insert(2,);
+  /// pool[key1] += 1;
+  /// 
+  /// let changes = pool.take_changes();
+  /// assert!(changes.inserted().contains(&key2,));
+  /// assert!(changes.modified().contains(&key1,));
+  /// assert!(pool.take_changes().is_empty());
+  /// ```
+  pub fn track_changes(&mut self, track: bool,) {
+    self.tracker.changes = if track { Some(self.tracker.changes.take().unwrap_or_default(),) } else { None };
+  }
+  /// Returns `true` if this TypePool is recording changes.
+  #[inline]
+  pub fn is_tracking_changes(&self,) -> bool { self.tracker.changes.is_some() }
+  /// Returns the changes recorded since changes were last taken and starts recording
+  /// afresh.
+  /// 
+  /// Returns no changes if this TypePool is not recording changes.
+  pub fn take_changes(&mut self,) -> Changes<T,> {
+    match &mut self.tracker.changes {
+      Some(changes,) => mem::take(changes,),
+      None => Changes::default(),
+    }
+  }
+  /// Registers `observer` to be called with every value inserted into this TypePool.
+  pub fn on_insert<F,>(&mut self, observer: F,) -> ObserverId
+    where F: FnMut(PoolKey<T,>, &T,) + Send + Sync + 'static, {
+    let id = self.tracker.next_observer();
+
+    self.tracker.on_insert.push((id, Box::new(observer,),),);
+    id
+  }
+  /// Registers `observer` to be called with every value removed from this TypePool.
+  pub fn on_remove<F,>(&mut self, observer: F,) -> ObserverId
+    where F: FnMut(PoolKey<T,>, &T,) + Send + Sync + 'static, {
+    let id = self.tracker.next_observer();
+
+    self.tracker.on_remove.push((id, Box::new(observer,),),);
+    id
+  }
+  /// Unregisters the observer `id`.
+  /// 
+  /// Returns `false` if the observer was not registered.
+  pub fn remove_observer(&mut self, id: ObserverId,) -> bool {
+    let len = self.tracker.on_insert.len() + self.tracker.on_remove.len();
+
+    self.tracker.on_insert.retain(|(observer, _,),| *observer != id,);
+    self.tracker.on_remove.retain(|(observer, _,),| *observer != id,);
+    len != self.tracker.on_insert.len() + self.tracker.on_remove.len()
+  }
+  /// Records that every value may have been modified.
+  pub(crate) fn modified_all(&mut self,) {
+    if self.tracker.changes.is_none() { return }
+
+    let pool_id = self.pool_id;
+    for (index, generation, _,) in self.pool.iter() {
+      self.tracker.modified(PoolKey(index, pool_id, generation, PhantomData,),);
+    }
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use std::sync::{Arc, Mutex,};
+
+  #[test]
+  fn test_track_changes() {
+    let (mut pool, keys,) = TypePool::from_iter(0..5,);
+
+    assert!(pool.take_changes().is_empty(), "`TypePool` recorded changes before tracking",);
+    pool.track_changes(true,);
+
+    let key = pool.insert(5,);
+    pool[keys[0]] += 1;
+    pool.get_disjoint_mut([keys[1], key],).unwrap();
+    pool.remove(keys[2],);
+    pool.get_mut(keys[3],).unwrap();
+    pool.remove(keys[3],);
+
+    let changes = pool.take_changes();
+    assert_eq!(changes.inserted(), &[key].iter().copied().collect(), "`TypePool` recorded wrong inserts",);
+    assert_eq!(changes.modified(), &[keys[0], keys[1]].iter().copied().collect(), "`TypePool` recorded wrong modifications",);
+    assert_eq!(changes.removed(), &[keys[2], keys[3]].iter().copied().collect(), "`TypePool` recorded wrong removals",);
+
+    let temp = pool.insert(6,);
+    pool.remove(temp,);
+    for _ in pool.values_mut() {}
+    let changes = pool.take_changes();
+    assert!(changes.inserted().is_empty() && changes.removed().is_empty(), "`TypePool` recorded a temporary value",);
+    assert_eq!(changes.modified().len(), pool.len(), "`TypePool::values_mut` did not record modifications",);
+
+    pool.retain(|retained, _,| retained != key,);
+    let changes = pool.take_changes();
+    assert_eq!(changes.removed(), &[key].iter().copied().collect(), "`TypePool::retain` recorded wrong removals",);
+    assert_eq!(changes.modified().len(), pool.len(), "`TypePool::retain` did not record kept values as modified",);
+
+    pool[keys[0]] += 1;
+    let _ = pool.transaction(|tx,| -> Result<(), _> {
+      tx.remove(keys[0],);
+      tx.remove(keys[4],);
+      Err(())
+    },);
+    let changes = pool.take_changes();
+    assert!(changes.inserted().is_empty() && changes.removed().is_empty(), "`TypePool` recorded a rolled back removal",);
+    assert_eq!(changes.modified(), &[keys[0]].iter().copied().collect(), "`TypePool` lost a modification before a rolled back removal",);
+
+    pool.track_changes(false,);
+    pool.insert(7,);
+    assert!(pool.take_changes().is_empty(), "`TypePool` recorded changes after tracking stopped",);
+  }
+  #[test]
+  fn test_observers() {
+    let events = Arc::new(Mutex::new(Vec::new(),),);
+    let mut pool = TypePool::new();
+    let log = events.clone();
+    let on_insert = pool.on_insert(move |_, &value,| log.lock().unwrap().push(('+', value,),),);
+    let log = events.clone();
+    pool.on_remove(move |_, &value,| log.lock().unwrap().push(('-', value,),),);
+
+    let key = pool.insert(1,);
+    pool.insert(2,);
+    pool.remove(key,);
+    pool.retain(|_, _,| false,);
+    assert!(pool.remove_observer(on_insert,), "`TypePool::remove_observer` did not find the observer",);
+    assert!(!pool.remove_observer(on_insert,), "`TypePool::remove_observer` removed an observer twice",);
+    pool.insert(3,);
+    pool.drain();
+
+    assert_eq!(*events.lock().unwrap(), [('+', 1,), ('+', 2,), ('-', 1,), ('-', 2,), ('-', 3,)], "`TypePool` observers missed events",);
+
+    let key = pool.insert(4,);
+    let log = events.clone();
+    pool.on_insert(move |_, &value,| log.lock().unwrap().push(('+', value,),),);
+    let _ = pool.transaction(|tx,| -> Result<(), _> { tx.remove(key,); Err(()) },);
+    assert_eq!(events.lock().unwrap()[5..], [('-', 4,), ('+', 4,)], "`TypePool` observers missed a rolled back removal",);
+  }
+}
@@ -0,0 +1,259 @@
+//! Defines the [Transaction]s which group [TypePool] mutations so they can be undone.
+
+use crate::{TypePool, PoolKey, PoolError, storage::{PoolStorage, DefaultStorage,},};
+use std::{ops, collections::HashSet,};
+
+/// A mutation made by a [Transaction] and the state needed to undo it.
+enum Undo<T,> {
+  /// A value was inserted under the key.
+  Insert(PoolKey<T,>,),
+  /// The value was removed from under the key.
+  Remove(PoolKey<T,>, T,),
+  /// The value of the key was handed out mutably while it held the saved value.
+  Modify(PoolKey<T,>, T,),
+}
+
+/// A set of mutations of a [TypePool] which are undone unless they are committed.
+/// 
+/// A Transaction derefs to the [TypePool] for read only access.
+pub struct Transaction<'a, T, S = DefaultStorage<T,>,>
+  where S: PoolStorage<T,>, {
+  pool: &'a mut TypePool<T, S,>,
+  /// The mutations made so far in the order they were made.
+  undo: Vec<Undo<T,>>,
+  /// The keys whose current values are already restored by `undo`.
+  saved: HashSet<PoolKey<T,>>,
+}
+
+impl<'a, T, S,> Transaction<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  /// Inserts `value` into the TypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value.
+  /// 
+  /// # Panics
+  /// 
+  /// If the TypePool is full.
+  pub fn insert(&mut self, value: T,) -> PoolKey<T,> {
+    self.try_insert(value,).unwrap_or_else(|(e, _,),| panic!("{}", e),)
+  }
+  /// Attempts to insert `value` into the TypePool.
+  /// 
+  /// Returns the [PoolKey] of the inserted value or `value` if the TypePool is full.
+  pub fn try_insert(&mut self, value: T,) -> Result<PoolKey<T,>, (PoolError, T,)> {
+    let key = self.pool.try_insert(value,)?;
+
+    self.undo.push(Undo::Insert(key,),);
+    self.saved.insert(key,);
+    Ok(key)
+  }
+  /// Removes the value mapped too `key`.
+  /// 
+  /// Returns `None` if the value has already been removed.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  /// 
+  /// # Panics
+  /// 
+  /// If `key` is not owned by the TypePool.
+  pub fn remove(&mut self, key: PoolKey<T,>,) -> Option<&T> {
+    assert!(self.pool.owns_key(&key,), "`Transaction::remove` `key` must be owned by this pool",);
+
+    self.try_remove(key,).ok()
+  }
+  /// Attempts to remove the value mapped too `key`.
+  /// 
+  /// The removed value is kept until the Transaction ends so it can be restored.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to remove.
+  pub fn try_remove(&mut self, key: PoolKey<T,>,) -> Result<&T, PoolError> {
+    let value = self.pool.try_remove(key,)?;
+
+    self.undo.push(Undo::Remove(key, value,),);
+    match self.undo.last() {
+      Some(Undo::Remove(_, value,),) => Ok(value,),
+      _ => unreachable!(),
+    }
+  }
+  /// Undoes every mutation made so far.
+  fn rollback(&mut self,) {
+    //Undoing in reverse restores the storage exactly as it was.
+    while let Some(undo,) = self.undo.pop() {
+      match undo {
+        Undo::Insert(key,) => { self.pool.remove(key,); },
+        Undo::Remove(key, value,) => self.pool.restore(key, value,),
+        Undo::Modify(key, value,) => self.pool[key] = value,
+      }
+    }
+    self.saved.clear();
+  }
+}
+
+impl<'a, T, S,> Transaction<'a, T, S,>
+  where T: Clone, S: PoolStorage<T,>, {
+  /// Returns a mutable reference to the value mapped too `key`.
+  /// 
+  /// The first time a value is accessed mutably it is cloned so it can be restored.
+  /// 
+  /// # Params
+  /// 
+  /// key --- The key of the value to get.
+  pub fn get_mut(&mut self, key: PoolKey<T,>,) -> Result<&mut T, PoolError> {
+    let value = self.pool.get_mut(key,)?;
+
+    if self.saved.insert(key,) { self.undo.push(Undo::Modify(key, value.clone(),),) }
+
+    Ok(value)
+  }
+}
+
+impl<'a, T, S,> ops::Deref for Transaction<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  type Target = TypePool<T, S,>;
+
+  #[inline]
+  fn deref(&self,) -> &Self::Target { self.pool }
+}
+
+impl<'a, T, S,> ops::Index<PoolKey<T,>> for Transaction<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  type Output = T;
+
+  #[inline]
+  fn index(&self, key: PoolKey<T,>,) -> &Self::Output { &self.pool[key] }
+}
+
+impl<'a, T, S,> ops::IndexMut<PoolKey<T,>> for Transaction<'a, T, S,>
+  where T: Clone, S: PoolStorage<T,>, {
+  #[inline]
+  fn index_mut(&mut self, key: PoolKey<T,>,) -> &mut Self::Output {
+    self.get_mut(key,).unwrap_or_else(|e,| panic!("`Transaction::index_mut` {}", e),)
+  }
+}
+
+impl<'a, T, S,> Drop for Transaction<'a, T, S,>
+  where S: PoolStorage<T,>, {
+  fn drop(&mut self,) { self.rollback() }
+}
+
+impl<T, S,> TypePool<T, S,>
+  where S: PoolStorage<T,>, {
+  /// Runs `f` with a [Transaction] over this TypePool.
+  /// 
+  /// If `f` returns `Err` or panics every mutation it made is undone: removed values are
+  /// restored under their original [PoolKey]s, modified values are restored and the keys
+  /// of inserted values are left removed.
+  /// 
+  /// # Params
+  /// 
+  /// f --- Mutates the TypePool through the Transaction.
+  /// 
+  /// # Example
+  /// 
+  /// ```
+  /// use type_pool::TypePool;
+  /// 
+  /// let (mut pool, keys,) = TypePool::from_iter(0..3,);
+  /// let result = pool.transaction(|tx,| {
+  ///   let key = tx.insert(3,);
+  /// 
+  ///   tx.remove(keys[0],);
+  ///   tx[keys[1]] += 10;
+  ///   if tx[keys[1]] > 10 { Err(key) } else { Ok(()) }
+  /// },);
+  /// let key = result.unwrap_err();
+  /// 
+  /// assert!(!pool.contains_key(&key,));
+  /// assert_eq!(pool[keys[0]], 0);
+  /// assert_eq!(pool[keys[1]], 1);
+  /// ```
+  pub fn transaction<F, R, E,>(&mut self, f: F,) -> Result<R, E>
+    where F: FnOnce(&mut Transaction<'_, T, S,>,) -> Result<R, E>, {
+    let mut tx = Transaction { pool: self, undo: Vec::new(), saved: HashSet::new(), };
+    let result = f(&mut tx,);
+
+    //Committing forgets the mutations so dropping the Transaction keeps them.
+    if result.is_ok() { tx.undo.clear() }
+
+    result
+  }
+}
+
+#[cfg(test,)]
+mod tests {
+  use super::*;
+  use crate::{KeyState, storage::{HashStorage, ArrayStorage,},};
+  use std::{marker::PhantomData, panic::{self, AssertUnwindSafe,},};
+
+  /// Checks that a failed transaction leaves the pool unchanged.
+  fn rollback<S,>()
+    where S: PoolStorage<i32,>, {
+    let mut pool = TypePool::<i32, S,>::default();
+    let keys = (0..4).map(|value,| pool.insert(value,),).collect::<Vec<_>>();
+    pool.remove(keys[3],);
+
+    let result = pool.transaction(|tx,| -> Result<(), _> {
+      let a = tx.insert(10,);
+      let b = tx.insert(11,);
+
+      tx[keys[0]] += 100;
+      tx[keys[0]] += 100;
+      assert_eq!(tx.remove(keys[1],), Some(&1), "`Transaction::remove` returned wrong value",);
+      tx[b] = 12;
+      tx.remove(a,);
+      tx[keys[2]] = 20;
+      tx.remove(keys[2],);
+      Err((a, b,),)
+    },);
+    let (a, b,) = result.unwrap_err();
+
+    assert_eq!(pool.len(), 3, "`Transaction` did not undo every mutation",);
+    assert_eq!(pool.get(keys[0],), Ok(&0), "`Transaction` did not restore a modified value",);
+    assert_eq!(pool.get(keys[1],), Ok(&1), "`Transaction` did not restore a removed value",);
+    assert_eq!(pool.get(keys[2],), Ok(&2), "`Transaction` did not restore a modified and removed value",);
+    assert_eq!(pool.key_state(&keys[3],), KeyState::Removed, "`Transaction` revived a value removed before it",);
+    assert_eq!(pool.key_state(&a,), KeyState::Removed, "`Transaction` kept an inserted key",);
+    assert_eq!(pool.key_state(&b,), KeyState::Removed, "`Transaction` kept an inserted key",);
+
+    let key = pool.insert(13,);
+    assert!(key != a && key != b, "`TypePool::insert` reissued a key from a failed transaction",);
+  }
+  #[test]
+  fn test_rollback() {
+    rollback::<DefaultStorage<_,>>();
+    rollback::<HashStorage<_,>>();
+    rollback::<ArrayStorage<_, 8,>>();
+  }
+  #[test]
+  fn test_commit() {
+    let (mut pool, keys,) = TypePool::from_iter(0..3,);
+    let key = pool.transaction(|tx,| {
+      let missing = PoolKey(keys[0].0, keys[0].1, usize::MAX, PhantomData,);
+      assert_eq!(tx.remove(missing,), None, "`Transaction::remove` removed a missing key",);
+      tx.remove(keys[0],);
+      tx[keys[1]] = 10;
+      Ok::<_, ()>(tx.insert(3,),)
+    },).unwrap();
+
+    assert!(!pool.contains_key(&keys[0],), "`Transaction` undid a committed remove",);
+    assert_eq!(pool[keys[1]], 10, "`Transaction` undid a committed modification",);
+    assert_eq!(pool[key], 3, "`Transaction` undid a committed insert",);
+  }
+  #[test]
+  fn test_panic_rollback() {
+    let (mut pool, keys,) = TypePool::from_iter(0..3,);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| pool.transaction(|tx,| -> Result<(), ()> {
+      tx.remove(keys[0],);
+      tx.insert(3,);
+      panic!("failed")
+    },),),);
+
+    assert!(result.is_err(), "`TypePool::transaction` caught the panic",);
+    assert_eq!(pool.len(), 3, "`Transaction` did not undo every mutation",);
+    assert_eq!(pool[keys[0]], 0, "`Transaction` did not restore a removed value",);
+  }
+}